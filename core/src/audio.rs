@@ -1,8 +1,10 @@
 use eyre::{bail, Context, ContextCompat, Result};
 use hound::{SampleFormat, WavReader};
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 use std::process::Stdio;
 use std::{path::PathBuf, process::Command};
+use tempfile::TempPath;
 use which::which;
 
 #[cfg(windows)]
@@ -95,6 +97,314 @@ pub fn normalize(input: PathBuf, output: PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Run ffmpeg with the given args and return its stderr. fail with the stderr if it exits with error
+fn run_ffmpeg(args: &[&str], loglevel: &str) -> Result<String> {
+    let ffmpeg_path = find_ffmpeg_path().context("ffmpeg not found")?;
+    tracing::debug!("ffmpeg path is {}", ffmpeg_path.display());
+
+    let mut cmd = Command::new(ffmpeg_path);
+    cmd.args(args)
+        .args(["-hide_banner", "-y", "-loglevel", loglevel])
+        .stdin(Stdio::null());
+
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    let output = cmd.output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        bail!(
+            "ffmpeg failed: {:?} args: {:?}",
+            stderr.chars().take(1000).collect::<String>(),
+            cmd.get_args()
+        );
+    }
+    Ok(stderr)
+}
+
+/// Integrated loudness in LUFS measured by ffmpeg loudnorm filter
+#[derive(Debug, Clone, Copy)]
+pub struct Loudness {
+    pub input: f32,
+    pub output: f32,
+}
+
+/// Parse the json summary printed by loudnorm with print_format=json
+fn parse_loudnorm_summary(stderr: &str) -> Result<Loudness> {
+    let start = stderr.rfind('{').context("loudnorm summary not found")?;
+    let end = stderr.rfind('}').context("loudnorm summary not found")?;
+    let summary: serde_json::Value = serde_json::from_str(&stderr[start..=end]).context("parse loudnorm summary")?;
+    let get = |key: &str| -> Result<f32> {
+        summary[key]
+            .as_str()
+            .context(format!("{} missing in loudnorm summary", key))?
+            .parse::<f32>()
+            .context(format!("parse {}", key))
+    };
+    Ok(Loudness {
+        input: get("input_i")?,
+        output: get("output_i")?,
+    })
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ConvertOptions {
+    /// Normalize loudness (EBU R128) to the target LUFS
+    pub loudnorm_target: Option<f32>,
+    /// Extract audio from this time
+    pub start_secs: Option<f32>,
+    /// Extract audio until this time
+    pub end_secs: Option<f32>,
+    /// Adjust the volume by this gain in dB (before loudness normalization)
+    pub gain_db: Option<f32>,
+}
+
+/// Convert to 16KHz mono wav for whisper. returns the measured loudness when normalized
+pub fn convert_to_whisper_wav(input: &Path, output: &Path, options: &ConvertOptions) -> Result<Option<Loudness>> {
+    let mut args = Vec::new();
+    if let Some(start_secs) = options.start_secs {
+        args.push("-ss".to_string());
+        args.push(start_secs.to_string());
+    }
+    if let Some(end_secs) = options.end_secs {
+        args.push("-to".into());
+        args.push(end_secs.to_string());
+    }
+    args.extend([
+        "-i".to_string(),
+        input.to_str().context("tostr")?.to_string(),
+        "-ar".into(),
+        "16000".into(),
+        "-ac".into(),
+        "1".into(),
+        "-c:a".into(),
+        "pcm_s16le".into(),
+    ]);
+    let mut filters = Vec::new();
+    if let Some(gain_db) = options.gain_db {
+        filters.push(format!("volume={}dB", gain_db));
+    }
+    if let Some(target) = options.loudnorm_target {
+        filters.push(format!("loudnorm=I={}:TP=-1.5:LRA=11:print_format=json", target));
+    }
+    if !filters.is_empty() {
+        args.push("-af".into());
+        args.push(filters.join(","));
+    }
+    args.push(output.to_str().context("tostr")?.to_string());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    // loudnorm prints the summary in info level
+    let loglevel = if options.loudnorm_target.is_some() { "info" } else { "error" };
+    let stderr = run_ffmpeg(&args, loglevel)?;
+    if !output.exists() {
+        bail!("seems like ffmpeg failed for some reason. output not exists")
+    }
+    if options.loudnorm_target.is_some() {
+        let loudness = parse_loudnorm_summary(&stderr)?;
+        tracing::debug!("loudness: {:?}", loudness);
+        return Ok(Some(loudness));
+    }
+    Ok(None)
+}
+
+/// Temp wav file which is removed when the path is dropped
+fn temp_wav_path() -> Result<TempPath> {
+    Ok(tempfile::Builder::new().suffix(".wav").tempfile()?.into_temp_path())
+}
+
+/// Split audio into 16KHz mono wav file per channel. returns the paths ordered by channel index.
+/// the files are removed when the paths are dropped
+pub fn split_channels(input: &Path) -> Result<Vec<TempPath>> {
+    let converted = temp_wav_path()?;
+    run_ffmpeg(
+        &[
+            "-i",
+            input.to_str().context("tostr")?,
+            "-ar",
+            "16000",
+            "-c:a",
+            "pcm_s16le",
+            converted.to_str().context("tostr")?,
+        ],
+        "error",
+    )?;
+
+    let reader = WavReader::open(&converted).context("failed to read file")?;
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    tracing::debug!("split {} channels of {}", channels, input.display());
+    let samples = reader
+        .into_samples::<i16>()
+        .map(|x| x.context("sample"))
+        .collect::<Result<Vec<i16>>>()?;
+    drop(converted);
+
+    let mono_spec = hound::WavSpec { channels: 1, ..spec };
+    let mut paths = Vec::new();
+    for channel in 0..channels {
+        let path = temp_wav_path()?;
+        let mut writer = hound::WavWriter::create(&path, mono_spec)?;
+        for sample in samples.iter().skip(channel).step_by(channels) {
+            writer.write_sample(*sample)?;
+        }
+        writer.finalize()?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Size of the `fmt ` fields used for the byte rate. larger chunks (eg. WAVE_FORMAT_EXTENSIBLE) are skipped
+const WAV_FMT_SIZE: u32 = 16;
+/// Larger `fmt ` chunks are malformed
+const WAV_FMT_MAX_SIZE: u32 = 1024;
+
+/// Duration of WAV file from the `fmt ` and `data` chunk headers.
+/// None when it isn't a WAV file or the header has no usable size (eg. streamed WAV with 0 or 0xFFFFFFFF data size)
+pub(crate) fn wav_duration(path: &Path) -> Result<Option<f64>> {
+    let mut file = std::fs::File::open(path).context("failed to open file")?;
+    let mut header = [0u8; 12];
+    if file.read_exact(&mut header).is_err() || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Ok(None);
+    }
+    let mut byte_rate = None;
+    let mut chunk_header = [0u8; 8];
+    while file.read_exact(&mut chunk_header).is_ok() {
+        let size = u32::from_le_bytes(chunk_header[4..8].try_into()?);
+        // Chunks are padded to even size
+        let padded_size = u64::from(size) + u64::from(size % 2);
+        match &chunk_header[0..4] {
+            b"fmt " => {
+                if !(WAV_FMT_SIZE..=WAV_FMT_MAX_SIZE).contains(&size) {
+                    return Ok(None);
+                }
+                let mut fmt = [0u8; WAV_FMT_SIZE as usize];
+                file.read_exact(&mut fmt).context("failed to read fmt chunk")?;
+                byte_rate = Some(u32::from_le_bytes(fmt[8..12].try_into()?));
+                file.seek(SeekFrom::Current((padded_size - u64::from(WAV_FMT_SIZE)) as i64))?;
+            }
+            b"data" => {
+                if size == 0 || size == u32::MAX {
+                    return Ok(None);
+                }
+                return Ok(byte_rate.filter(|rate| *rate > 0).map(|rate| size as f64 / rate as f64));
+            }
+            _ => {
+                file.seek(SeekFrom::Current(padded_size as i64))?;
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Parse `Duration: 00:01:02.34` printed by ffmpeg
+fn parse_ffmpeg_duration(stderr: &str) -> Option<f64> {
+    let start = stderr.find("Duration: ")? + "Duration: ".len();
+    let duration = stderr[start..].split(',').next()?.trim();
+    let mut seconds = 0.0;
+    for part in duration.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Audio duration in seconds. WAV headers are read directly, other formats are probed with ffmpeg
+pub fn get_audio_duration(path: &Path) -> Result<f64> {
+    if let Some(duration) = wav_duration(path)? {
+        return Ok(duration);
+    }
+    let input = path.to_str().context("tostr")?;
+    let stderr = run_ffmpeg(&["-i", input, "-t", "0", "-f", "null", "-"], "info")?;
+    parse_ffmpeg_duration(&stderr).context("duration not found in ffmpeg output")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_secs: f64,
+}
+
+/// Parse sample rate and channels of the first audio stream printed by ffmpeg
+/// (eg. `Stream #0:0: Audio: mp3, 44100 Hz, stereo, fltp, 128 kb/s`)
+pub fn parse_ffmpeg_audio_stream(stderr: &str) -> Option<(u32, u16)> {
+    let line = stderr
+        .lines()
+        .find(|line| line.contains("Stream #") && line.contains("Audio:"))?;
+    let fields: Vec<&str> = line.split("Audio:").nth(1)?.split(',').map(str::trim).collect();
+    let sample_rate_index = fields.iter().position(|field| field.ends_with(" Hz"))?;
+    let sample_rate = fields[sample_rate_index].trim_end_matches(" Hz").parse().ok()?;
+    let layout = fields.get(sample_rate_index + 1)?;
+    let channels = match *layout {
+        "mono" => 1,
+        "stereo" => 2,
+        "2.1" | "3.0" => 3,
+        "quad" | "4.0" => 4,
+        "5.0" | "5.0(side)" => 5,
+        "5.1" | "5.1(side)" => 6,
+        "7.1" => 8,
+        layout => layout.trim_end_matches(" channels").parse().ok()?,
+    };
+    Some((sample_rate, channels))
+}
+
+/// Sample rate, channels and duration of the audio. WAV headers are read directly, other formats are probed with ffmpeg
+pub fn probe_audio(path: &Path) -> Result<AudioInfo> {
+    if let Ok(reader) = WavReader::open(path) {
+        let spec = reader.spec();
+        return Ok(AudioInfo {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            duration_secs: reader.duration() as f64 / spec.sample_rate as f64,
+        });
+    }
+    let input = path.to_str().context("tostr")?;
+    let stderr = run_ffmpeg(&["-i", input, "-t", "0", "-f", "null", "-"], "info")?;
+    let (sample_rate, channels) = parse_ffmpeg_audio_stream(&stderr).context("audio stream not found in ffmpeg output")?;
+    Ok(AudioInfo {
+        sample_rate,
+        channels,
+        duration_secs: parse_ffmpeg_duration(&stderr).context("duration not found in ffmpeg output")?,
+    })
+}
+
+/// Detect audio / video container by the file magic bytes
+pub fn detect_format(path: &Path) -> Result<Option<&'static str>> {
+    let mut header = [0u8; 64];
+    let mut file = std::fs::File::open(path).context("failed to open file")?;
+    let read = file.read(&mut header).context("failed to read file header")?;
+    let header = &header[..read];
+    let format = if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+        Some("wav")
+    } else if header.len() >= 12 && &header[4..8] == b"ftyp" {
+        // Audio only MP4 files use M4A brand
+        if &header[8..12] == b"M4A " {
+            Some("m4a")
+        } else {
+            Some("mp4")
+        }
+    } else if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xF6 == 0xF0 {
+        // ADTS sync word with layer 0. checked before MP3 frame sync which also matches it
+        Some("aac")
+    } else if header.starts_with(b"ID3") || (header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0) {
+        Some("mp3")
+    } else if header.starts_with(b"OggS") {
+        Some("ogg")
+    } else if header.starts_with(b"fLaC") {
+        Some("flac")
+    } else if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        // Matroska, WebM sets the doctype to webm
+        if header.windows(4).any(|window| window == b"webm") {
+            Some("webm")
+        } else {
+            Some("mkv")
+        }
+    } else {
+        None
+    };
+    Ok(format)
+}
+
 pub fn parse_wav_file(path: &PathBuf) -> Result<Vec<i16>> {
     tracing::debug!("wav reader read from {:?}", path);
     let reader = WavReader::open(path).context("failed to read file")?;
@@ -1,20 +1,293 @@
+use crate::pii_filter::PiiPattern;
+use crate::transcript::VttStyle;
 use core::fmt;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Deserialize, Serialize, ToSchema)]
+#[derive(Deserialize, Serialize, ToSchema, Validate, Clone, Default)]
 pub struct TranscribeOptions {
     pub path: String,
     pub lang: Option<String>,
     pub verbose: Option<bool>,
 
+    #[validate(range(min = 1))]
     pub n_threads: Option<i32>,
     pub init_prompt: Option<String>,
+    /// Read the initial prompt from this file. overrides `init_prompt`
+    pub initial_prompt_file: Option<String>,
+    /// Return the tokens of the initial prompt without transcribing. `path` isn't used
+    pub tokenize_only: Option<bool>,
+    #[validate(range(min = 0.0, max = 1.0))]
     pub temperature: Option<f32>,
     pub translate: Option<bool>,
+    /// Max tokens of the previous text used as prompt for the next window.
+    /// `-1` means no limit (default), positive values are the token count and `0` is invalid
     pub max_text_ctx: Option<i32>,
     pub word_timestamps: Option<bool>,
+    /// Return every text token of the segments with its timestamps and probability.
+    /// unlike `word_timestamps` the segments are not split, sub-word tokens are kept as is
+    pub token_level_timestamps: Option<bool>,
     pub max_sentence_len: Option<i32>,
+    /// Token IDs to remove from the output text.
+    /// IDs depend on the model vocabulary (eg. tokens of ` [MUSIC]`, ` [APPLAUSE]`, ` [LAUGHTER]`),
+    /// tokenize the words with the same model to find them. Must be in range `0..n_vocab`
+    pub suppress_tokens: Option<Vec<i32>>,
+    /// Drop segments with no speech probability above this threshold (default 0.6).
+    /// These are often hallucinations on silent or music only audio
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub no_speech_threshold: Option<f32>,
+    /// Transcribe each channel independently and label segments by channel.
+    /// Can't be used together with diarization
+    pub multichannel: Option<bool>,
+    /// Label two speakers by the speaker turns of a tinydiarize model (eg. ggml-small.en-tdrz.bin).
+    /// Faster than diarization but less accurate and the labels only alternate between `0` and `1`.
+    /// Can't be used together with diarization
+    pub tdrz: Option<bool>,
+    /// Speaker labels for the channels (default: `Channel 0`, `Channel 1`, ...)
+    pub channel_names: Option<Vec<String>>,
+    /// Transcribe like `multichannel` and return the transcript of every channel too. fails on mono audio
+    pub split_channels: Option<bool>,
+    /// Normalize loudness (EBU R128) before transcribe (default true).
+    /// Files already in whisper format (16KHz mono wav) are normalized only when explicitly enabled
+    pub normalize_audio: Option<bool>,
+    /// Target integrated loudness for normalization in LUFS (default -23)
+    pub normalize_target_lufs: Option<f32>,
+    /// Adjust the volume before transcribe. clamped to -20..40 dB.
+    /// Loudness normalization is skipped unless `normalize_audio` is enabled explicitly
+    pub audio_gain_db: Option<f32>,
+    /// Split segments longer than this into shorter segments
+    pub max_segment_duration_secs: Option<f32>,
+    /// Merge segments shorter than this into the adjacent segments
+    pub min_segment_duration_secs: Option<f32>,
+    /// Flag segments with high no speech probability and low confidence as possible hallucinations.
+    /// When enabled no_speech_threshold applies only if set explicitly
+    pub hallucination_filter: Option<bool>,
+    /// Drop the segments flagged by hallucination filter
+    pub remove_hallucinations: Option<bool>,
+    /// Transcribe from this time in the audio. segment timestamps stay relative to the original audio
+    #[validate(range(min = 0.0))]
+    pub start_secs: Option<f32>,
+    /// Transcribe until this time in the audio
+    pub end_secs: Option<f32>,
+    /// Domain words to bias the decoder towards. they're placed in the initial prompt
+    /// which increases the probability of these tokens where they fit the audio
+    pub hotwords: Option<Vec<String>>,
+    /// GPU device to transcribe on. a model loaded on another device is reloaded
+    pub gpu_device: Option<i32>,
+    /// Use flash attention for faster inference. applied when the model is loaded, a model loaded without it is reloaded.
+    /// Requires a compatible model and a build with GPU or BLAS backend which supports it
+    pub flash_attn: Option<bool>,
+    /// Align token timestamps with dynamic time warping. more accurate word timestamps than the default
+    /// token timestamps at the cost of extra memory and slower decoding. applied when the model is loaded
+    /// and requires an official whisper model. takes precedence over the `word_timestamps` timing
+    pub dtw_timestamps: Option<bool>,
+    /// Re-segment the output at sentence boundaries
+    pub sentence_segmentation: Option<bool>,
+    /// Join word segments into sentences timed from the first to the last word. requires `word_timestamps`
+    pub sentence_timestamps: Option<bool>,
+    /// Abort with timeout error when transcription takes longer than this. at most a week
+    #[validate(range(exclusive_min = 0.0, max = 604800.0))]
+    pub timeout_secs: Option<f32>,
+    /// What happens on timeout. `error` (default) fails, `partial` returns the segments transcribed before
+    /// the timeout with `truncated` and `status: partial` (error when there are none)
+    pub timeout_action: Option<String>,
+    /// `greedy` (default) or `beam_search`. greedy is significantly faster and suits real time use,
+    /// beam search keeps several candidate sequences which improves accuracy at the cost of speed
+    pub sampling_strategy: Option<String>,
+    /// Beams count for beam search (default: 5)
+    #[validate(range(min = 1))]
+    pub beam_size: Option<i32>,
+    /// Beam search patience factor (default: 1.0)
+    pub patience: Option<f32>,
+    /// Label segments with emotion when diarization is enabled. requires the `emotion` feature.
+    /// Labels are approximate since they're inferred from the speaker embeddings
+    pub emotion_detection: Option<bool>,
+    /// Emotion classifier model for `emotion_detection`
+    pub emotion_model_path: Option<String>,
+    /// Return at most this number of segments. the transcript is marked as truncated when segments are dropped
+    pub max_segment_count: Option<usize>,
+    /// Capitalize sentences and add missing sentence end punctuation to segments
+    pub auto_punctuate: Option<bool>,
+    /// Prefix segments which likely start a new speaker with `>>`, based on pauses and capitalization.
+    /// ignored when the segments have speakers from diarization
+    pub speaker_change_markers: Option<bool>,
+    /// Cue style of VTT output
+    pub vtt_style: Option<VttStyle>,
+    /// Mask profanity in the final segments text
+    pub profanity_filter: Option<bool>,
+    /// Replacement of filtered words (default `****`)
+    pub profanity_replacement: Option<String>,
+    /// Word list file, one word per line. defaults to the built in list of the `profanity-filter` feature
+    pub profanity_list_path: Option<String>,
+    /// Replace phone numbers, email addresses, SSNs and credit card numbers with their type in brackets (eg. `[EMAIL]`)
+    pub mask_pii: Option<bool>,
+    /// Extra patterns masked by `mask_pii`
+    pub pii_patterns: Option<Vec<PiiPattern>>,
+    /// Server queue priority from 0 (lowest) to 255 (highest), default 128.
+    /// Requests waiting for the model are transcribed by priority and then by arrival
+    pub priority: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigWarningKind {
+    /// Options are invalid or contradict each other. the transcription is rejected
+    Conflict,
+    /// An option has no effect with the other options
+    Incompatible,
+    /// The combination isn't supported by the model or build and may produce wrong results
+    Unsupported,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct ConfigWarning {
+    pub kind: ConfigWarningKind,
+    pub fields: Vec<String>,
+    pub message: String,
+}
+
+impl ConfigWarning {
+    fn new(kind: ConfigWarningKind, fields: &[&str], message: &str) -> Self {
+        Self {
+            kind,
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Check the options for combinations which are accepted by themselves but produce wrong results together.
+/// `diarize` tells if the options are used with diarization
+pub fn validate_options(options: &TranscribeOptions, diarize: bool) -> Vec<ConfigWarning> {
+    use ConfigWarningKind::*;
+    let enabled = |option: Option<bool>| option == Some(true);
+    let mut warnings = Vec::new();
+
+    // whisper asserts on these
+    if options
+        .max_text_ctx
+        .is_some_and(|max_text_ctx| max_text_ctx == 0 || max_text_ctx < -1)
+    {
+        warnings.push(ConfigWarning::new(
+            Conflict,
+            &["max_text_ctx"],
+            "max_text_ctx must be -1 (no limit) or a positive token count",
+        ));
+    }
+    if enabled(options.tdrz) && diarize {
+        warnings.push(ConfigWarning::new(
+            Conflict,
+            &["tdrz", "diarize"],
+            "tinydiarize and diarization both label speakers",
+        ));
+    }
+    if enabled(options.sentence_timestamps) && !enabled(options.word_timestamps) {
+        warnings.push(ConfigWarning::new(
+            Conflict,
+            &["sentence_timestamps", "word_timestamps"],
+            "sentence_timestamps requires word_timestamps",
+        ));
+    }
+    if let (Some(max), Some(min)) = (options.max_segment_duration_secs, options.min_segment_duration_secs) {
+        if max < min {
+            warnings.push(ConfigWarning::new(
+                Conflict,
+                &["max_segment_duration_secs", "min_segment_duration_secs"],
+                "max_segment_duration_secs is shorter than min_segment_duration_secs",
+            ));
+        }
+    }
+    if (enabled(options.multichannel) || enabled(options.split_channels)) && diarize {
+        warnings.push(ConfigWarning::new(
+            Conflict,
+            &["multichannel", "diarize"],
+            "multichannel labels speakers by channel and can't be used together with diarization",
+        ));
+    }
+
+    if enabled(options.word_timestamps) && enabled(options.dtw_timestamps) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["word_timestamps", "dtw_timestamps"],
+            "word timings are taken from DTW",
+        ));
+    }
+    if options.max_sentence_len.is_some() && !enabled(options.word_timestamps) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["max_sentence_len", "word_timestamps"],
+            "max_sentence_len applies only with word_timestamps",
+        ));
+    }
+    if options.normalize_target_lufs.is_some() && options.normalize_audio == Some(false) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["normalize_target_lufs", "normalize_audio"],
+            "normalize_target_lufs is ignored when normalize_audio is disabled",
+        ));
+    }
+    if options.audio_gain_db.is_some_and(|gain| gain != 0.0) && enabled(options.normalize_audio) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["audio_gain_db", "normalize_audio"],
+            "loudness normalization after the gain cancels it",
+        ));
+    }
+    if (options.beam_size.is_some() || options.patience.is_some()) && options.sampling_strategy.as_deref() != Some("beam_search")
+    {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["beam_size", "patience", "sampling_strategy"],
+            "beam_size and patience apply only with the beam_search sampling strategy",
+        ));
+    }
+    if (options.profanity_replacement.is_some() || options.profanity_list_path.is_some()) && !enabled(options.profanity_filter) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["profanity_replacement", "profanity_list_path", "profanity_filter"],
+            "profanity options are ignored when profanity_filter is disabled",
+        ));
+    }
+    if options.pii_patterns.is_some() && !enabled(options.mask_pii) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["pii_patterns", "mask_pii"],
+            "pii_patterns is ignored when mask_pii is disabled",
+        ));
+    }
+    if options.emotion_model_path.is_some() && !enabled(options.emotion_detection) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["emotion_model_path", "emotion_detection"],
+            "emotion_model_path is ignored when emotion_detection is disabled",
+        ));
+    }
+    if enabled(options.emotion_detection) && !diarize {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["emotion_detection", "diarize"],
+            "emotion labels are inferred from the speaker embeddings of diarization",
+        ));
+    }
+    if options.channel_names.is_some() && !enabled(options.multichannel) && !enabled(options.split_channels) {
+        warnings.push(ConfigWarning::new(
+            Incompatible,
+            &["channel_names", "multichannel"],
+            "channel_names applies only with multichannel or split_channels",
+        ));
+    }
+
+    // tinydiarize models are english only
+    if enabled(options.tdrz) && (enabled(options.translate) || options.lang.as_deref().is_some_and(|lang| lang != "en")) {
+        warnings.push(ConfigWarning::new(
+            Unsupported,
+            &["tdrz", "lang", "translate"],
+            "tinydiarize models support english transcription only",
+        ));
+    }
+
+    warnings
 }
 
 impl fmt::Debug for TranscribeOptions {
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeType {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct Change {
+    #[serde(rename = "type")]
+    pub change_type: ChangeType,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct WordDiff {
+    pub diff: Vec<Change>,
+    /// (substitutions + deletions + insertions) / words in `a`
+    pub word_error_rate: f32,
+    pub insertion_count: usize,
+    pub deletion_count: usize,
+    pub substitution_count: usize,
+}
+
+/// Word level diff from `a` (reference) to `b`
+pub fn diff_words(a: &str, b: &str) -> WordDiff {
+    let a_words: Vec<&str> = a.split_whitespace().collect();
+    let b_words: Vec<&str> = b.split_whitespace().collect();
+    let mut diff = Vec::new();
+    let (mut insertion_count, mut deletion_count, mut substitution_count) = (0, 0, 0);
+
+    let mut push = |change_type: ChangeType, words: &[&str]| {
+        if !words.is_empty() {
+            diff.push(Change {
+                change_type,
+                text: words.join(" "),
+            });
+        }
+    };
+    for op in capture_diff_slices(Algorithm::Myers, &a_words, &b_words) {
+        match op {
+            DiffOp::Equal { old_index, len, .. } => push(ChangeType::Equal, &a_words[old_index..old_index + len]),
+            DiffOp::Delete { old_index, old_len, .. } => {
+                deletion_count += old_len;
+                push(ChangeType::Delete, &a_words[old_index..old_index + old_len]);
+            }
+            DiffOp::Insert { new_index, new_len, .. } => {
+                insertion_count += new_len;
+                push(ChangeType::Insert, &b_words[new_index..new_index + new_len]);
+            }
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => {
+                let substituted = old_len.min(new_len);
+                substitution_count += substituted;
+                deletion_count += old_len - substituted;
+                insertion_count += new_len - substituted;
+                push(ChangeType::Delete, &a_words[old_index..old_index + old_len]);
+                push(ChangeType::Insert, &b_words[new_index..new_index + new_len]);
+            }
+        }
+    }
+
+    let errors = insertion_count + deletion_count + substitution_count;
+    let word_error_rate = if a_words.is_empty() {
+        if b_words.is_empty() {
+            0.0
+        } else {
+            1.0
+        }
+    } else {
+        errors as f32 / a_words.len() as f32
+    };
+    WordDiff {
+        diff,
+        word_error_rate,
+        insertion_count,
+        deletion_count,
+        substitution_count,
+    }
+}
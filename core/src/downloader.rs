@@ -38,34 +38,92 @@ impl Downloader {
         Downloader { client }
     }
 
+    /// Download to `<path>.part` and rename to `path` once completed.
+    /// If a partial download exists, resume it when the server supports range requests
     pub async fn download<F>(&mut self, url: &str, path: PathBuf, on_progress: F) -> Result<()>
     where
         F: Fn(u64, u64) -> bool,
     {
-        let res = self.client.get(url).send().await?;
-        let total_size = res
+        let mut part_path = path.clone().into_os_string();
+        part_path.push(".part");
+        let part_path = PathBuf::from(part_path);
+
+        let offset = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+        let mut req = self.client.get(url);
+        if offset > 0 {
+            req = req.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let res = req.send().await?;
+        if offset > 0 && res.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // Range starts at the end of the file. the partial download is already complete
+            let total = res
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(|range| range.strip_prefix("bytes */"))
+                .and_then(|total| total.parse::<u64>().ok());
+            if total.is_some_and(|total| total != offset) {
+                bail!(
+                    "Failed to resume download of '{}'. partial file size {} doesn't match",
+                    url,
+                    offset
+                );
+            }
+            tracing::info!("Partial download of {} is complete", url);
+            std::fs::rename(&part_path, &path).context(format!(
+                "Failed to rename {} to {}",
+                part_path.display(),
+                path.display()
+            ))?;
+            return Ok(());
+        }
+        if !res.status().is_success() {
+            bail!("Failed to download '{}': status {}", url, res.status());
+        }
+        let is_resumed = offset > 0
+            && res.status() == reqwest::StatusCode::PARTIAL_CONTENT
+            && res.headers().contains_key(reqwest::header::CONTENT_RANGE);
+        let content_length = res
             .content_length()
             .ok_or_eyre(format!("Failed to get content length from '{}'", url))?;
-        let mut file = std::fs::File::create(path.clone()).context(format!("Failed to create file {}", path.display()))?;
-        let mut downloaded: u64 = 0;
+
+        let (mut file, mut downloaded) = if is_resumed {
+            tracing::info!("Resume download of {} from offset {}", url, offset);
+            let file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&part_path)
+                .context(format!("Failed to open file {}", part_path.display()))?;
+            (file, offset)
+        } else {
+            if offset > 0 {
+                tracing::debug!("Server doesn't support range requests. start download from scratch");
+            }
+            let file = std::fs::File::create(&part_path).context(format!("Failed to create file {}", part_path.display()))?;
+            (file, 0)
+        };
+        let total_size = downloaded + content_length;
         let callback_limit = 1024 * 1024 * 2; // 1MB limit
-        let mut callback_offset = 0;
+        let mut callback_offset = downloaded;
         let mut stream = res.bytes_stream();
         while let Some(item) = stream.next().await {
             let chunk = item.context("Error while downloading file")?;
             file.write_all(&chunk)
-                .context(format!("Error while writing to file {}", path.display()))?;
+                .context(format!("Error while writing to file {}", part_path.display()))?;
             // Check if downloaded size is a multiple of 10MB
             if downloaded > callback_offset + callback_limit {
                 let is_abort_set = on_progress(downloaded, total_size);
                 if is_abort_set {
-                    break;
+                    // Keep the partial file so the download can be resumed
+                    return Ok(());
                 }
 
                 callback_offset = downloaded;
             }
             downloaded += chunk.len() as u64;
         }
+        file.flush()?;
+        drop(file);
+        std::fs::rename(&part_path, &path).context(format!("Failed to rename {} to {}", part_path.display(), path.display()))?;
         Ok(())
     }
 }
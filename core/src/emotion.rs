@@ -0,0 +1,42 @@
+use eyre::{bail, Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+pub const EMOTIONS: [&str; 6] = ["neutral", "happy", "sad", "angry", "fearful", "surprised"];
+
+/// Linear classifier over the speaker embedding. the model file is JSON with `labels`, `weights` (one row per label) and `bias`.
+/// Results are approximate: the embeddings are trained for speaker identity and carry only part of the prosody
+#[derive(Deserialize)]
+pub struct EmotionClassifier {
+    labels: Vec<String>,
+    weights: Vec<Vec<f32>>,
+    bias: Vec<f32>,
+}
+
+impl EmotionClassifier {
+    pub fn new(model_path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(model_path).context(format!("failed to read {}", model_path.display()))?;
+        let classifier: Self = serde_json::from_str(&content).context("invalid emotion model")?;
+        if classifier.labels.len() != classifier.weights.len() || classifier.labels.len() != classifier.bias.len() {
+            bail!("emotion model labels, weights and bias must have the same length")
+        }
+        if let Some(label) = classifier.labels.iter().find(|label| !EMOTIONS.contains(&label.as_str())) {
+            bail!("unknown emotion label {}. expected one of {}", label, EMOTIONS.join(", "))
+        }
+        Ok(classifier)
+    }
+
+    /// Most probable emotion and its softmax probability
+    pub fn classify(&self, embedding: &[f32]) -> Option<(String, f32)> {
+        let scores: Vec<f32> = self
+            .weights
+            .iter()
+            .zip(&self.bias)
+            .map(|(weights, bias)| weights.iter().zip(embedding).map(|(w, x)| w * x).sum::<f32>() + bias)
+            .collect();
+        let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let total: f32 = scores.iter().map(|score| (score - max).exp()).sum();
+        let (index, score) = scores.iter().enumerate().max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+        Some((self.labels[index].clone(), (score - max).exp() / total))
+    }
+}
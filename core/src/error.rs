@@ -0,0 +1,59 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Errors of model loading and transcription
+#[derive(Debug)]
+pub enum TranscribeError {
+    ModelLoadFailed {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// Audio file can't be read or converted
+    AudioDecodeError(eyre::Report),
+    /// Whisper or diarization failed
+    InferenceError(eyre::Report),
+    InvalidOptions {
+        field: String,
+        reason: String,
+    },
+    Aborted,
+    Timeout,
+}
+
+impl TranscribeError {
+    pub fn invalid_options(field: &str, reason: impl Into<String>) -> Self {
+        Self::InvalidOptions {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for TranscribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModelLoadFailed { path, source } => write!(f, "failed to load model {}: {}", path.display(), source),
+            Self::AudioDecodeError(error) => write!(f, "failed to decode audio: {:?}", error),
+            Self::InferenceError(error) => write!(f, "transcription failed: {:?}", error),
+            Self::InvalidOptions { field, reason } => write!(f, "invalid option {}: {}", field, reason),
+            Self::Aborted => write!(f, "transcription aborted"),
+            Self::Timeout => write!(f, "transcription timed out"),
+        }
+    }
+}
+
+impl std::error::Error for TranscribeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ModelLoadFailed { source, .. } => Some(source.as_ref()),
+            Self::AudioDecodeError(error) | Self::InferenceError(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<eyre::Report> for TranscribeError {
+    fn from(error: eyre::Report) -> Self {
+        Self::InferenceError(error)
+    }
+}
@@ -0,0 +1,227 @@
+use crate::config::TranscribeOptions;
+use crate::transcript::{Segment, Transcript};
+use eyre::{bail, Context, ContextCompat, Result};
+use serde_json::{json, Value};
+
+#[cfg(feature = "docx")]
+pub mod docx;
+
+/// Unsigned decimal number. signs and other characters are rejected
+fn parse_digits(digits: &str) -> Option<i64> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Parse `HH:MM:SS,mmm` / `MM:SS.mmm` into centiseconds
+fn parse_timestamp(timestamp: &str) -> Result<i64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let (time, fraction) = timestamp.split_once('.').unwrap_or((&timestamp, "0"));
+    let mut seconds: i64 = 0;
+    for part in time.split(':') {
+        seconds = parse_digits(part)
+            .and_then(|part| seconds.checked_mul(60)?.checked_add(part))
+            .context(format!("invalid timestamp {}", timestamp))?;
+    }
+    let fraction: String = fraction.chars().take(3).collect();
+    let milliseconds = parse_digits(&format!("{:0<3}", fraction)).context(format!("invalid timestamp {}", timestamp))?;
+    seconds
+        .checked_mul(100)
+        .and_then(|centiseconds| centiseconds.checked_add(milliseconds / 10))
+        .context(format!("invalid timestamp {}", timestamp))
+}
+
+/// Parse `start --> end [settings]` timing line
+fn parse_timing(line: &str) -> Result<(i64, i64)> {
+    let (start, end) = line.split_once("-->").context("timing line without -->")?;
+    let end = end.split_whitespace().next().context("missing end timestamp")?;
+    Ok((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Cues separated by blank lines. lines before the timing line (cue number / identifier) are ignored
+fn parse_cues(content: &str, parse_text: impl Fn(&str) -> (String, Option<String>)) -> Result<Transcript> {
+    let content = content.replace("\r\n", "\n");
+    let mut segments = Vec::new();
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let Some(timing) = lines.next() else {
+            continue;
+        };
+        let (start, stop) = parse_timing(timing)?;
+        let text = lines.collect::<Vec<_>>().join("\n");
+        let (text, speaker) = parse_text(&text);
+        segments.push(Segment {
+            start,
+            stop,
+            text: format!(" {}", text),
+            speaker,
+            ..Default::default()
+        });
+    }
+    if segments.is_empty() {
+        bail!("no cues found")
+    }
+    Ok(Transcript {
+        segments,
+        ..Default::default()
+    })
+}
+
+pub fn parse_srt(content: &str) -> Result<Transcript> {
+    parse_cues(content, |text| (text.to_string(), None))
+}
+
+/// Parse WebVTT. speaker is taken from `<v Speaker>` voice span and other tags are removed
+pub fn parse_vtt(content: &str) -> Result<Transcript> {
+    if !content.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        bail!("missing WEBVTT header")
+    }
+    parse_cues(content, |text| {
+        let speaker = text.find("<v ").and_then(|start| {
+            text[start + 3..]
+                .split_once('>')
+                .map(|(speaker, _)| speaker.trim().to_string())
+        });
+        let mut plain = String::new();
+        let mut in_tag = false;
+        for c in text.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                c if !in_tag => plain.push(c),
+                _ => {}
+            }
+        }
+        let plain = plain.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&");
+        (plain, speaker)
+    })
+}
+
+pub fn parse(content: &str, format: &str) -> Result<Transcript> {
+    let transcript: Transcript = match format {
+        "srt" => parse_srt(content)?,
+        "vtt" => parse_vtt(content)?,
+        "json" => serde_json::from_str(content).context("invalid transcript json")?,
+        _ => bail!("unsupported input format {}", format),
+    };
+    for segment in &transcript.segments {
+        if segment.start < 0 || segment.stop < segment.start {
+            bail!("invalid segment timestamps {} --> {}", segment.start, segment.stop)
+        }
+    }
+    Ok(transcript)
+}
+
+/// Content type of the output format
+pub fn content_type(format: &str) -> &'static str {
+    match format {
+        "srt" => "application/x-subrip",
+        "vtt" => "text/vtt",
+        "json" => "application/json",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+/// Convert transcript file content between formats
+pub fn convert(content: &str, from_format: &str, to_format: &str) -> Result<String> {
+    let transcript = parse(content, from_format)?;
+    format_transcript(&transcript, to_format)
+}
+
+pub fn format_transcript(transcript: &Transcript, to_format: &str) -> Result<String> {
+    let output = match to_format {
+        "srt" => transcript.as_srt(),
+        "vtt" => transcript.as_vtt(false, None),
+        "txt" => transcript.as_text(),
+        "json" => transcript.as_json()?,
+        "ass" => transcript.as_ass(false),
+        "audacity" => transcript.as_audacity_labels(),
+        _ => bail!("unsupported output format {}", to_format),
+    };
+    Ok(output)
+}
+
+/// Transcript in the JSON shape of the whisper.cpp server `/inference` endpoint.
+/// `verbose` adds the task, language and duration like `response_format=verbose_json`,
+/// and the words when `word_timestamps` is enabled (every segment is a single word)
+pub fn whisper_cpp_json(transcript: &Transcript, options: &TranscribeOptions, verbose: bool) -> Value {
+    let segments: Vec<Value> = transcript
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(id, segment)| {
+            json!({
+                "id": id,
+                // 10ms frames like whisper
+                "seek": segment.start,
+                "start": segment.start as f64 / 100.0,
+                "end": segment.stop as f64 / 100.0,
+                "text": segment.text,
+                "tokens": [],
+                "temperature": options.temperature.unwrap_or_default(),
+                "no_speech_prob": segment.no_speech_prob.unwrap_or_default(),
+            })
+        })
+        .collect();
+    let mut value = json!({
+        "text": transcript.as_text(),
+        "segments": segments,
+    });
+    if verbose {
+        let task = match options.translate {
+            Some(true) => "translate",
+            _ => "transcribe",
+        };
+        let duration = transcript.segments.last().map(|segment| segment.stop).unwrap_or_default();
+        value["task"] = json!(task);
+        value["language"] = json!(options.lang.as_deref().unwrap_or("auto"));
+        value["duration"] = json!(duration as f64 / 100.0);
+        if options.word_timestamps == Some(true) {
+            let words: Vec<Value> = transcript
+                .segments
+                .iter()
+                .map(|segment| {
+                    json!({
+                        "word": segment.text.trim(),
+                        "start": segment.start as f64 / 100.0,
+                        "end": segment.stop as f64 / 100.0,
+                        "probability": segment.confidence.unwrap_or_default(),
+                    })
+                })
+                .collect();
+            value["words"] = json!(words);
+        }
+    }
+    value
+}
+
+/// Build `multipart/mixed` body with one part per (format, content).
+/// The response content type is `multipart/mixed; boundary=<boundary>`
+pub fn multipart_mixed(parts: &[(&str, String)], boundary: &str) -> String {
+    let mut body = String::new();
+    for (format, content) in parts {
+        let extension = if *format == "audacity" { "txt" } else { format };
+        body.push_str(&format!("--{}\r\n", boundary));
+        body.push_str(&format!("Content-Type: {}\r\n", content_type(format)));
+        body.push_str(&format!(
+            "Content-Disposition: attachment; filename=\"transcript.{}\"\r\n\r\n",
+            extension
+        ));
+        body.push_str(content);
+        body.push_str("\r\n");
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    body
+}
+
+/// Convert transcript file content to several formats as `multipart/mixed` body
+pub fn convert_multipart(content: &str, from_format: &str, to_formats: &[&str], boundary: &str) -> Result<String> {
+    let transcript = parse(content, from_format)?;
+    let parts = to_formats
+        .iter()
+        .map(|format| Ok((*format, format_transcript(&transcript, format)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(multipart_mixed(&parts, boundary))
+}
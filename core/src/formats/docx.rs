@@ -0,0 +1,67 @@
+use crate::transcript::{format_timestamp, Transcript};
+use docx_rs::{Comment, Docx, Paragraph, Run, Style, StyleType, TableOfContents};
+use eyre::{eyre, Result};
+
+/// Word document with a title, a table of contents of the speaker sections when the transcript has speakers
+/// and a paragraph per segment. segment timestamps are added as margin comments
+pub fn transcript_to_docx(transcript: &Transcript, title: &str) -> Result<Vec<u8>> {
+    let duration = transcript.segments.last().map(|segment| segment.stop).unwrap_or_default();
+    let has_speakers = transcript.segments.iter().any(|segment| segment.speaker.is_some());
+
+    let mut docx = Docx::new()
+        .add_style(
+            Style::new("Heading1", StyleType::Paragraph)
+                .name("Heading 1")
+                .bold()
+                .size(32)
+                .outline_lvl(0),
+        )
+        .add_style(
+            Style::new("Heading2", StyleType::Paragraph)
+                .name("Heading 2")
+                .bold()
+                .size(26)
+                .outline_lvl(1),
+        )
+        .add_paragraph(Paragraph::new().style("Heading1").add_run(Run::new().add_text(title)))
+        .add_paragraph(
+            Paragraph::new().add_run(Run::new().add_text(format!("Duration: {}", format_timestamp(duration, true, ".")))),
+        );
+    if has_speakers {
+        docx = docx.add_table_of_contents(TableOfContents::new().heading_styles_range(2, 2));
+    }
+
+    let mut current_speaker: Option<&str> = None;
+    for (i, segment) in transcript.segments.iter().enumerate() {
+        // New section whenever the speaker changes
+        if has_speakers && (i == 0 || segment.speaker.as_deref() != current_speaker) {
+            current_speaker = segment.speaker.as_deref();
+            docx = docx.add_paragraph(
+                Paragraph::new()
+                    .style("Heading2")
+                    .add_run(Run::new().add_text(current_speaker.unwrap_or("Unknown"))),
+            );
+        }
+        let comment_id = i + 1;
+        let timestamp = format!(
+            "{} - {}",
+            format_timestamp(segment.start, true, "."),
+            format_timestamp(segment.stop, true, ".")
+        );
+        let comment = Comment::new(comment_id)
+            .author("vibe")
+            .add_paragraph(Paragraph::new().add_run(Run::new().add_text(timestamp)));
+        docx = docx.add_paragraph(
+            Paragraph::new()
+                .add_comment_start(comment)
+                .add_run(Run::new().add_text(segment.text.trim()))
+                .add_comment_end(comment_id),
+        );
+    }
+
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    docx.build()
+        .pack(&mut buffer)
+        .map_err(|e| eyre!("failed to build docx: {:?}", e))?;
+    Ok(buffer.into_inner())
+}
@@ -0,0 +1,88 @@
+use eyre::Result;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct GpuDevice {
+    /// Device index as used by `gpu_device`
+    pub index: i32,
+    pub name: String,
+    pub backend: String,
+    pub memory_total_bytes: Option<u64>,
+    pub memory_free_bytes: Option<u64>,
+}
+
+/// Query NVIDIA devices with nvidia-smi
+#[cfg(feature = "cuda")]
+fn cuda_devices() -> Result<Vec<GpuDevice>> {
+    use eyre::{bail, Context};
+
+    let output = std::process::Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=index,name,memory.total,memory.free",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .context("failed to run nvidia-smi")?;
+    if !output.status.success() {
+        bail!("nvidia-smi failed: {}", String::from_utf8_lossy(&output.stderr))
+    }
+    let mib = |value: &str| value.trim().parse::<u64>().ok().map(|v| v * 1024 * 1024);
+    let devices = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            Some(GpuDevice {
+                index: fields[0].trim().parse().ok()?,
+                name: fields[1].trim().to_string(),
+                backend: "cuda".into(),
+                memory_total_bytes: mib(fields[2]),
+                memory_free_bytes: mib(fields[3]),
+            })
+        })
+        .collect();
+    Ok(devices)
+}
+
+/// Query Metal devices with system_profiler
+#[cfg(feature = "metal")]
+fn metal_devices() -> Result<Vec<GpuDevice>> {
+    use eyre::Context;
+
+    let output = std::process::Command::new("system_profiler")
+        .args(["SPDisplaysDataType", "-json"])
+        .output()
+        .context("failed to run system_profiler")?;
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).context("invalid system_profiler output")?;
+    let devices = value["SPDisplaysDataType"]
+        .as_array()
+        .map(|displays| {
+            displays
+                .iter()
+                .enumerate()
+                .map(|(index, display)| GpuDevice {
+                    index: index as i32,
+                    name: display["sppci_model"].as_str().unwrap_or("Metal").to_string(),
+                    backend: "metal".into(),
+                    memory_total_bytes: None,
+                    memory_free_bytes: None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(devices)
+}
+
+/// GPU devices available for the enabled backends. empty when built without GPU support
+pub fn gpu_devices() -> Result<Vec<GpuDevice>> {
+    #[allow(unused_mut)]
+    let mut devices = Vec::new();
+    #[cfg(feature = "cuda")]
+    devices.extend(cuda_devices()?);
+    #[cfg(feature = "metal")]
+    devices.extend(metal_devices()?);
+    Ok(devices)
+}
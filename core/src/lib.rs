@@ -1,8 +1,23 @@
 pub mod audio;
 pub mod config;
+pub mod diff;
 pub mod downloader;
+#[cfg(feature = "emotion")]
+pub mod emotion;
+pub mod error;
+pub mod formats;
+pub mod gpu;
+pub mod merge;
+pub mod pii_filter;
+pub mod post_process;
+pub mod search;
+pub mod segment_utils;
+pub mod summarize;
 pub mod transcribe;
 pub mod transcript;
 
+#[cfg(feature = "translate")]
+pub mod translate;
+
 #[cfg(test)]
 mod test;
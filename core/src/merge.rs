@@ -0,0 +1,60 @@
+use crate::transcript::{Segment, Transcript};
+use eyre::{bail, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct MergedTranscript {
+    pub transcript: Transcript,
+    /// Overlapping segments found after merging
+    pub warnings: Vec<String>,
+}
+
+/// Merge transcripts of consecutive parts. `time_offsets` (seconds) are added to the segments of every part,
+/// missing offsets are treated as 0
+pub fn merge_transcripts(transcripts: Vec<Transcript>, time_offsets: &[f64]) -> Result<MergedTranscript> {
+    if time_offsets.len() > transcripts.len() {
+        bail!(
+            "got {} time offsets for {} transcripts",
+            time_offsets.len(),
+            transcripts.len()
+        )
+    }
+    let mut processing_time_sec = 0;
+    let mut segments: Vec<Segment> = Vec::new();
+    for (i, transcript) in transcripts.into_iter().enumerate() {
+        // Segment timestamps are in centiseconds
+        let offset = (time_offsets.get(i).copied().unwrap_or_default() * 100.0).round() as i64;
+        processing_time_sec += transcript.processing_time_sec;
+        segments.extend(transcript.segments.into_iter().map(|mut segment| {
+            segment.start += offset;
+            segment.stop += offset;
+            segment
+        }));
+    }
+    segments.sort_by_key(|segment| segment.start);
+
+    let warnings = segments
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[1].start < pair[0].stop)
+        .map(|(i, pair)| {
+            format!(
+                "segment {} ({:.2}s) overlaps segment {} which ends at {:.2}s",
+                i + 1,
+                pair[1].start as f64 / 100.0,
+                i,
+                pair[0].stop as f64 / 100.0
+            )
+        })
+        .collect();
+
+    Ok(MergedTranscript {
+        transcript: Transcript {
+            processing_time_sec,
+            segments,
+            ..Default::default()
+        },
+        warnings,
+    })
+}
@@ -0,0 +1,102 @@
+use crate::transcript::Segment;
+use eyre::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Organization specific pattern. matches are replaced with the uppercase name in brackets (eg. `[EMPLOYEE_ID]`)
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct PiiPattern {
+    pub name: String,
+    /// Regular expression in the syntax of the regex crate
+    pub pattern: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct PiiEntityCount {
+    #[serde(rename = "type")]
+    pub entity_type: String,
+    pub count: usize,
+}
+
+// Ordered so longer digit sequences are masked before the phone pattern sees them
+static BUILT_IN_PATTERNS: Lazy<Vec<(&str, Regex)>> = Lazy::new(|| {
+    [
+        ("EMAIL", r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b"),
+        ("SSN", r"\b\d{3}-\d{2}-\d{4}\b"),
+        ("CREDIT_CARD", r"\b(?:\d[ -]?){12,18}\d\b"),
+        ("PHONE", r"(?:\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]?\d{3}[\s.-]?\d{4}\b"),
+    ]
+    .into_iter()
+    .map(|(name, pattern)| (name, Regex::new(pattern).expect("built in PII pattern")))
+    .collect()
+});
+
+/// Luhn checksum of card numbers. filters out other long numbers
+fn is_luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| match (i % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => *digit,
+        })
+        .sum();
+    sum % 10 == 0
+}
+
+/// Compile the custom patterns of the options
+pub fn compile_patterns(patterns: &[PiiPattern]) -> Result<Vec<(String, Regex)>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let regex = Regex::new(&pattern.pattern).context(format!("invalid pattern {}", pattern.name))?;
+            Ok((pattern.name.to_uppercase(), regex))
+        })
+        .collect()
+}
+
+/// Replace personally identifiable information with its type in brackets (eg. `[EMAIL]`).
+/// Custom patterns apply after the built in ones. returns the count of every masked type
+pub fn mask_pii(segments: &mut [Segment], custom_patterns: &[(String, Regex)]) -> Vec<PiiEntityCount> {
+    let patterns = BUILT_IN_PATTERNS
+        .iter()
+        .map(|(name, regex)| (*name, regex))
+        .chain(custom_patterns.iter().map(|(name, regex)| (name.as_str(), regex)));
+    let mut counts: Vec<PiiEntityCount> = Vec::new();
+    for (name, regex) in patterns {
+        let mut count = 0;
+        for segment in segments.iter_mut() {
+            let text = regex.replace_all(&segment.text, |captures: &regex::Captures| {
+                let matched = &captures[0];
+                if name == "CREDIT_CARD" && !is_luhn_valid(matched) {
+                    return matched.to_string();
+                }
+                count += 1;
+                format!("[{}]", name)
+            });
+            segment.text = text.into_owned();
+        }
+        if count > 0 {
+            counts.push(PiiEntityCount {
+                entity_type: name.to_string(),
+                count,
+            });
+        }
+    }
+    counts
+}
+
+/// Add the counts of another transcript
+pub fn merge_counts(counts: &mut Vec<PiiEntityCount>, other: &[PiiEntityCount]) {
+    for entity in other {
+        match counts.iter_mut().find(|count| count.entity_type == entity.entity_type) {
+            Some(count) => count.count += entity.count,
+            None => counts.push(entity.clone()),
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::transcript::{Segment, Transcript};
+use eyre::{Context, Result};
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Plain text with a blank line (paragraph break) wherever the pause between segments exceeds `gap_secs`
+pub fn text_with_paragraphs(transcript: &Transcript, gap_secs: f32) -> String {
+    // Segment timestamps are in centiseconds
+    let gap = (gap_secs * 100.0) as i64;
+    let mut text = String::new();
+    let mut prev_stop: Option<i64> = None;
+    for segment in &transcript.segments {
+        if let Some(prev_stop) = prev_stop {
+            if segment.start - prev_stop > gap {
+                text.push_str("\n\n");
+            } else {
+                text.push(' ');
+            }
+        }
+        text.push_str(segment.text.trim());
+        prev_stop = Some(segment.stop);
+    }
+    text
+}
+
+const SENTENCE_END: [char; 6] = ['.', '?', '!', '…', '。', '？'];
+
+/// Word ends with sentence terminal punctuation, ignoring closing quotes and brackets
+fn ends_sentence(word: &str) -> bool {
+    word.trim()
+        .trim_end_matches(['"', '\'', '”', '’', ')', ']', '»'])
+        .ends_with(['.', '?', '!', '…', '。', '？', '！'])
+}
+
+/// Next word starts a sentence unless its first letter is lowercase. scripts without case always start one
+fn starts_sentence(word: &str) -> bool {
+    word.chars().find(|c| c.is_alphabetic()).map_or(true, |c| !c.is_lowercase())
+}
+
+/// Group word segments (from `word_timestamps`) into sentences. each sentence starts at its first word
+/// and ends at its last word. a sentence ends with `.`, `?` or `!` followed by a capitalized word, or on speaker change
+pub fn sentence_timestamps(words: Vec<Segment>) -> Vec<Segment> {
+    let mut sentences: Vec<Segment> = Vec::new();
+    let mut current: Option<Segment> = None;
+    for (i, word) in words.iter().enumerate() {
+        current = match current.take() {
+            Some(mut sentence) if sentence.speaker == word.speaker => {
+                sentence.stop = word.stop;
+                sentence.text.push_str(&word.text);
+                if let (Some(tokens), Some(word_tokens)) = (sentence.tokens.as_mut(), word.tokens.as_ref()) {
+                    tokens.extend(word_tokens.iter().cloned());
+                }
+                Some(sentence)
+            }
+            other => {
+                sentences.extend(other);
+                Some(Segment {
+                    start: word.start,
+                    stop: word.stop,
+                    text: word.text.clone(),
+                    speaker: word.speaker.clone(),
+                    tokens: word.tokens.clone(),
+                    ..Default::default()
+                })
+            }
+        };
+        let is_end = ends_sentence(&word.text) && words.get(i + 1).map_or(true, |next| starts_sentence(&next.text));
+        if is_end {
+            sentences.extend(current.take());
+        }
+    }
+    sentences.extend(current);
+    sentences
+}
+
+/// Pause before a segment which suggests a new speaker (centiseconds)
+const SPEAKER_CHANGE_GAP: i64 = 150;
+
+/// Heuristic speaker change markers for transcripts without diarization. a segment which follows a pause longer
+/// than 1.5 seconds and starts with a capitalized word is prefixed with `>>`. returns the number of markers
+pub fn mark_speaker_changes(segments: &mut [Segment]) -> usize {
+    let mut count = 0;
+    let mut prev_stop: Option<i64> = None;
+    for segment in segments.iter_mut() {
+        let capitalized = segment
+            .text
+            .chars()
+            .find(|c| c.is_alphabetic())
+            .is_some_and(char::is_uppercase);
+        if prev_stop.is_some_and(|prev_stop| segment.start - prev_stop > SPEAKER_CHANGE_GAP) && capitalized {
+            let text = segment.text.trim_start();
+            let indent = &segment.text[..segment.text.len() - text.len()];
+            segment.text = format!("{}>> {}", indent, text);
+            count += 1;
+        }
+        prev_stop = Some(segment.stop);
+    }
+    count
+}
+
+/// Uppercase the first letter of every sentence in the text
+fn capitalize_sentences(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for sentence in text.split_sentence_bounds() {
+        let mut capitalized = false;
+        for grapheme in sentence.graphemes(true) {
+            if !capitalized && grapheme.chars().any(char::is_alphabetic) {
+                result.push_str(&grapheme.to_uppercase());
+                capitalized = true;
+            } else {
+                result.push_str(grapheme);
+            }
+        }
+    }
+    result
+}
+
+/// Capitalize sentences and end every segment with `.`, `?` or `!`.
+/// Rule based, segments which already end with punctuation are left as is
+pub fn auto_punctuate(segments: &mut [Segment]) {
+    for segment in segments.iter_mut() {
+        let trimmed = segment.text.trim_end();
+        let Some(last) = trimmed.chars().last() else {
+            continue;
+        };
+        let mut text = capitalize_sentences(trimmed);
+        if matches!(last, ',' | ';' | ':') {
+            text.pop();
+            text.push('.');
+        } else if !SENTENCE_END.contains(&last) && last.is_alphanumeric() {
+            text.push('.');
+        }
+        segment.text = text;
+    }
+}
+
+#[cfg(feature = "profanity-filter")]
+const PROFANITY_LIST: &[&str] = &[
+    "ass",
+    "asshole",
+    "bastard",
+    "bitch",
+    "bullshit",
+    "cock",
+    "crap",
+    "cunt",
+    "damn",
+    "dick",
+    "fuck",
+    "fucked",
+    "fucker",
+    "fucking",
+    "motherfucker",
+    "piss",
+    "pussy",
+    "shit",
+    "shitty",
+    "slut",
+    "twat",
+    "whore",
+];
+
+/// Words of the profanity list file, one per line. lines starting with `#` are ignored
+pub fn read_profanity_list(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .map(|line| line.trim().to_lowercase())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect())
+}
+
+/// Profanity list from the file or the built in list of the `profanity-filter` feature
+pub fn profanity_list(path: Option<&Path>) -> Result<Option<Vec<String>>> {
+    if let Some(path) = path {
+        return read_profanity_list(path).map(Some);
+    }
+    #[cfg(feature = "profanity-filter")]
+    return Ok(Some(PROFANITY_LIST.iter().map(|word| word.to_string()).collect()));
+    #[cfg(not(feature = "profanity-filter"))]
+    Ok(None)
+}
+
+/// Replace whole words of the list (case insensitive) with the replacement. returns the number of replaced words
+pub fn filter_profanity(segments: &mut [Segment], words: &[String], replacement: &str) -> usize {
+    let mut filtered = 0;
+    for segment in segments.iter_mut() {
+        let mut text = String::with_capacity(segment.text.len());
+        for word in segment.text.split_word_bounds() {
+            if words.contains(&word.to_lowercase()) {
+                text.push_str(replacement);
+                filtered += 1;
+            } else {
+                text.push_str(word);
+            }
+        }
+        segment.text = text;
+    }
+    filtered
+}
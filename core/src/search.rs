@@ -0,0 +1,98 @@
+use crate::transcript::Segment;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
+pub struct SearchOptions {
+    pub query: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Match only when the query isn't part of a longer word
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Number of segments to return before and after every match
+    #[serde(default)]
+    pub context_segments: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct SearchSegment {
+    pub segment_index: usize,
+    /// Seconds
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct SearchMatch {
+    #[serde(flatten)]
+    pub segment: SearchSegment,
+    /// Match position in characters of the segment text
+    pub match_start: usize,
+    pub match_end: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<SearchSegment>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<SearchSegment>,
+}
+
+fn search_segment(segments: &[Segment], index: usize) -> SearchSegment {
+    let segment = &segments[index];
+    SearchSegment {
+        segment_index: index,
+        start: segment.start as f64 / 100.0,
+        end: segment.stop as f64 / 100.0,
+        text: segment.text.trim().to_string(),
+    }
+}
+
+fn chars_eq(a: char, b: char, case_sensitive: bool) -> bool {
+    a == b || (!case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+/// Character ranges of the query in text
+fn find_all(text: &[char], query: &[char], options: &SearchOptions) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    if query.is_empty() || query.len() > text.len() {
+        return matches;
+    }
+    let mut i = 0;
+    while i + query.len() <= text.len() {
+        let end = i + query.len();
+        let found = text[i..end]
+            .iter()
+            .zip(query)
+            .all(|(a, b)| chars_eq(*a, *b, options.case_sensitive));
+        let is_word = !options.whole_word
+            || ((i == 0 || !text[i - 1].is_alphanumeric()) && (end == text.len() || !text[end].is_alphanumeric()));
+        if found && is_word {
+            matches.push((i, end));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+/// Find the query in segments text with unicode aware case folding
+pub fn search_segments(segments: &[Segment], options: &SearchOptions) -> Vec<SearchMatch> {
+    let query: Vec<char> = options.query.chars().collect();
+    let mut result = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let text: Vec<char> = segment.text.trim().chars().collect();
+        for (match_start, match_end) in find_all(&text, &query, options) {
+            let before = index.saturating_sub(options.context_segments)..index;
+            let after = index + 1..(index + 1 + options.context_segments).min(segments.len());
+            result.push(SearchMatch {
+                segment: search_segment(segments, index),
+                match_start,
+                match_end,
+                context_before: before.map(|i| search_segment(segments, i)).collect(),
+                context_after: after.map(|i| search_segment(segments, i)).collect(),
+            });
+        }
+    }
+    result
+}
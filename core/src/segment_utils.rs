@@ -0,0 +1,157 @@
+use crate::transcript::Segment;
+
+/// Word with timestamps interpolated from the segment by characters count
+struct TimedWord<'a> {
+    text: &'a str,
+    start: i64,
+    stop: i64,
+}
+
+fn interpolate_words(segment: &Segment) -> Vec<TimedWord<'_>> {
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let total_chars: usize = words.iter().map(|w| w.chars().count()).sum();
+    let duration = segment.stop - segment.start;
+    let mut chars_before = 0;
+    words
+        .into_iter()
+        .map(|text| {
+            let chars = text.chars().count();
+            let start = segment.start + duration * chars_before as i64 / total_chars.max(1) as i64;
+            chars_before += chars;
+            let stop = segment.start + duration * chars_before as i64 / total_chars.max(1) as i64;
+            TimedWord { text, start, stop }
+        })
+        .collect()
+}
+
+/// Split segments longer than `max_duration` (centiseconds) at word boundaries.
+/// Word times are interpolated linearly across the segment. speaker labels are preserved
+pub fn split_long_segments(segments: Vec<Segment>, max_duration: i64) -> Vec<Segment> {
+    let mut result = Vec::new();
+    for segment in segments {
+        if segment.stop - segment.start <= max_duration || max_duration <= 0 {
+            result.push(segment);
+            continue;
+        }
+        let words = interpolate_words(&segment);
+        if words.len() < 2 {
+            result.push(segment);
+            continue;
+        }
+        let prefix = if segment.text.starts_with(char::is_whitespace) {
+            " "
+        } else {
+            ""
+        };
+
+        let mut chunks: Vec<Vec<&TimedWord>> = Vec::new();
+        for word in &words {
+            match chunks.last_mut() {
+                Some(chunk) if word.stop - chunk[0].start <= max_duration => chunk.push(word),
+                _ => chunks.push(vec![word]),
+            }
+        }
+        let chunks_count = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let start = if i == 0 { segment.start } else { chunk[0].start };
+            let stop = if i == chunks_count - 1 {
+                segment.stop
+            } else {
+                chunk[chunk.len() - 1].stop
+            };
+            let text = chunk.iter().map(|w| w.text).collect::<Vec<_>>().join(" ");
+            result.push(Segment {
+                start,
+                stop,
+                text: format!("{}{}", prefix, text),
+                ..segment.clone()
+            });
+        }
+    }
+    result
+}
+
+fn is_sentence_end(word: &str) -> bool {
+    let word = word.trim_end_matches(['"', '\'', ')', ']', '”', '’', '»']);
+    word.ends_with(['.', '!', '?', '…', '。', '！', '？'])
+}
+
+/// Re-segment at sentence boundaries. segment texts are concatenated and split after sentence terminal punctuation,
+/// timestamps come from the interpolated word times. a speaker change ends the sentence as well
+pub fn split_sentences(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut result: Vec<Segment> = Vec::new();
+    let mut current: Option<Segment> = None;
+    for segment in &segments {
+        for word in interpolate_words(segment) {
+            current = match current.take() {
+                Some(mut sentence) if sentence.speaker == segment.speaker => {
+                    sentence.stop = word.stop;
+                    sentence.text = format!("{} {}", sentence.text, word.text);
+                    Some(sentence)
+                }
+                other => {
+                    result.extend(other);
+                    Some(Segment {
+                        start: word.start,
+                        stop: word.stop,
+                        text: format!(" {}", word.text),
+                        speaker: segment.speaker.clone(),
+                        ..Default::default()
+                    })
+                }
+            };
+            if is_sentence_end(word.text) {
+                result.extend(current.take());
+            }
+        }
+    }
+    result.extend(current);
+    result
+}
+
+fn join_text(first: &str, second: &str) -> String {
+    let prefix = if first.starts_with(char::is_whitespace) { " " } else { "" };
+    format!("{}{} {}", prefix, first.trim(), second.trim())
+}
+
+/// Merge segments shorter than `min_duration` (centiseconds) into the closer adjacent segment.
+/// Adjacent segment of the same speaker is preferred
+pub fn merge_short_segments(mut segments: Vec<Segment>, min_duration: i64) -> Vec<Segment> {
+    let mut i = 0;
+    while i < segments.len() {
+        if segments.len() < 2 || segments[i].stop - segments[i].start >= min_duration {
+            i += 1;
+            continue;
+        }
+        let segment = &segments[i];
+        let prev = i.checked_sub(1).map(|p| &segments[p]);
+        let next = segments.get(i + 1);
+        let merge_into_prev = match (prev, next) {
+            (Some(prev), Some(next)) => {
+                let same_prev = prev.speaker == segment.speaker;
+                let same_next = next.speaker == segment.speaker;
+                if same_prev != same_next {
+                    same_prev
+                } else {
+                    segment.start - prev.stop <= next.start - segment.stop
+                }
+            }
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        let segment = segments.remove(i);
+        if merge_into_prev {
+            let prev = &mut segments[i - 1];
+            prev.stop = prev.stop.max(segment.stop);
+            prev.text = join_text(&prev.text, &segment.text);
+            // Merged segment may still be short
+            i -= 1;
+        } else {
+            let next = &mut segments[i];
+            next.start = next.start.min(segment.start);
+            next.text = join_text(&segment.text, &next.text);
+        }
+    }
+    segments
+}
@@ -0,0 +1,191 @@
+use crate::transcript::Segment;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
+use utoipa::ToSchema;
+
+const DEFAULT_MAX_SENTENCES: usize = 5;
+const KEY_TOPICS_COUNT: usize = 5;
+/// Shorter words are ignored when scoring
+const MIN_WORD_LEN: usize = 3;
+const STOP_WORDS: [&str; 48] = [
+    "the", "and", "that", "this", "with", "for", "are", "was", "were", "you", "your", "have", "has", "had", "not", "but", "they",
+    "them", "their", "there", "what", "when", "which", "who", "will", "would", "can", "could", "should", "from", "about", "into",
+    "just", "like", "been", "being", "our", "out", "all", "also", "then", "than", "its", "it's", "i'm", "don't", "yeah", "okay",
+];
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryStyle {
+    /// One line per sentence prefixed with `- `
+    #[default]
+    Bullet,
+    Paragraph,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
+pub struct SummarizeOptions {
+    /// Number of sentences in the summary (default 5)
+    pub max_sentences: Option<usize>,
+    #[serde(default)]
+    pub style: SummaryStyle,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct Summary {
+    pub summary: String,
+    /// Most important words of the transcript
+    pub key_topics: Vec<String>,
+    /// Seconds from the first segment start to the last segment end
+    pub duration_summarized_secs: f64,
+}
+
+fn words(sentence: &str) -> Vec<String> {
+    sentence
+        .unicode_words()
+        .map(|word| word.to_lowercase())
+        .filter(|word| word.chars().count() >= MIN_WORD_LEN && !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Sentences of the transcript text. segments may end in the middle of a sentence
+pub fn sentences(segments: &[Segment]) -> Vec<String> {
+    let text = segments
+        .iter()
+        .map(|segment| segment.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    text.unicode_sentences()
+        .map(|sentence| sentence.trim().to_string())
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+pub fn duration_secs(segments: &[Segment]) -> f64 {
+    match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (last.stop - first.start).max(0) as f64 / 100.0,
+        _ => 0.0,
+    }
+}
+
+pub fn format_summary(sentences: &[String], style: SummaryStyle) -> String {
+    match style {
+        SummaryStyle::Bullet => sentences
+            .iter()
+            .map(|sentence| format!("- {}", sentence))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        SummaryStyle::Paragraph => sentences.join(" "),
+    }
+}
+
+/// Extractive summary. every sentence is a document for IDF and the term frequency is taken over the whole transcript.
+/// Sentences are scored by the mean TF-IDF of their words and the best are returned in their original order
+pub fn summarize_segments(segments: &[Segment], options: &SummarizeOptions) -> Summary {
+    let sentences = sentences(segments);
+    let sentence_words: Vec<Vec<String>> = sentences.iter().map(|sentence| words(sentence)).collect();
+
+    let mut term_frequency: HashMap<&str, usize> = HashMap::new();
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for words in &sentence_words {
+        for word in words {
+            *term_frequency.entry(word.as_str()).or_default() += 1;
+        }
+        for word in words.iter().map(String::as_str).collect::<HashSet<_>>() {
+            *document_frequency.entry(word).or_default() += 1;
+        }
+    }
+    let total_words: usize = sentence_words.iter().map(Vec::len).sum();
+    let tf_idf: HashMap<&str, f64> = term_frequency
+        .iter()
+        .map(|(word, count)| {
+            let idf = (sentences.len() as f64 / document_frequency[word] as f64).ln() + 1.0;
+            (*word, *count as f64 / total_words as f64 * idf)
+        })
+        .collect();
+
+    let scores: Vec<f64> = sentence_words
+        .iter()
+        .map(|words| {
+            if words.is_empty() {
+                return 0.0;
+            }
+            words.iter().map(|word| tf_idf[word.as_str()]).sum::<f64>() / words.len() as f64
+        })
+        .collect();
+
+    let max_sentences = options.max_sentences.unwrap_or(DEFAULT_MAX_SENTENCES);
+    let mut ranked: Vec<usize> = (0..sentences.len()).collect();
+    ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]).then(a.cmp(b)));
+    ranked.truncate(max_sentences);
+    ranked.sort();
+    let selected: Vec<String> = ranked.into_iter().map(|index| sentences[index].clone()).collect();
+
+    let mut topics: Vec<(&str, f64)> = tf_idf.into_iter().collect();
+    topics.sort_by(|(a_word, a), (b_word, b)| b.total_cmp(a).then(a_word.cmp(b_word)));
+
+    Summary {
+        summary: format_summary(&selected, options.style),
+        key_topics: topics
+            .into_iter()
+            .take(KEY_TOPICS_COUNT)
+            .map(|(word, _)| word.to_string())
+            .collect(),
+        duration_summarized_secs: duration_secs(segments),
+    }
+}
+
+/// Abstractive summary with an OpenAI compatible chat completions endpoint (Ollama serves one in /v1/chat/completions).
+/// Key topics are still extracted locally
+#[cfg(feature = "summarize")]
+pub async fn summarize_with_model(
+    url: &str,
+    model: &str,
+    segments: &[Segment],
+    options: &SummarizeOptions,
+) -> eyre::Result<Summary> {
+    use eyre::{bail, Context, OptionExt};
+    use serde_json::{json, Value};
+
+    let max_sentences = options.max_sentences.unwrap_or(DEFAULT_MAX_SENTENCES);
+    let style = match options.style {
+        SummaryStyle::Bullet => "a bullet list with one sentence per line, each line starting with \"- \"",
+        SummaryStyle::Paragraph => "a single paragraph",
+    };
+    let body = json!({
+        "model": model,
+        "stream": false,
+        "messages": [
+            {
+                "role": "system",
+                "content": format!("Summarize the transcript in at most {} sentences as {}. Reply with the summary only.", max_sentences, style),
+            },
+            {
+                "role": "user",
+                "content": sentences(segments).join(" "),
+            },
+        ],
+    });
+    let client = reqwest::Client::new();
+    let res = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .context(format!("failed to send summarize request to {}", url))?;
+    let status = res.status();
+    let res_text = res.text().await?;
+    if !status.is_success() {
+        bail!("summarize request failed with status {}: {}", status, res_text);
+    }
+    let value: Value = serde_json::from_str(&res_text).context("failed to parse summarize response")?;
+    let summary = value["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_eyre("choices[0].message.content missing in response")?;
+    Ok(Summary {
+        summary: summary.trim().to_string(),
+        ..summarize_segments(segments, options)
+    })
+}
@@ -3,28 +3,32 @@ wget https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin
 cargo test --features "vulkan" -- --nocapture
 cargo test --release --features "vulkan" -- --nocapture
 */
-use crate::{config::TranscribeOptions, transcribe::create_context};
+use crate::error::TranscribeError;
+use crate::merge::merge_transcripts;
+use crate::pii_filter::{compile_patterns, mask_pii, PiiPattern};
+use crate::post_process::{auto_punctuate, filter_profanity, mark_speaker_changes, sentence_timestamps};
+use crate::segment_utils::{merge_short_segments, split_long_segments, split_sentences};
+use crate::summarize::{summarize_segments, SummarizeOptions, SummaryStyle};
+use crate::transcript::{Segment, Transcript, VttStyle};
+use crate::{
+    config::{validate_options, ConfigWarningKind, TranscribeOptions},
+    transcribe::{create_context, dtw_model_preset, initial_prompt, read_prompt_file, ContextOptions},
+};
 use serial_test::serial;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 use tracing_test::traced_test;
+use whisper_rs::DtwModelPreset;
 
 #[test]
 #[serial]
 #[traced_test]
 fn test_transcribe() {
-    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), None).unwrap();
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
     let options = &TranscribeOptions {
-        init_prompt: None,
         lang: Some("en".into()),
-        max_sentence_len: None,
         path: "../samples/short.wav".into(),
-        verbose: None,
-        max_text_ctx: None,
-        n_threads: None,
-        temperature: None,
-        translate: None,
-        word_timestamps: None,
+        ..Default::default()
     };
     let start = Instant::now();
     let result = crate::transcribe::transcribe(&ctx, options, None, None, None, None);
@@ -34,3 +38,714 @@ fn test_transcribe() {
         Instant::now().duration_since(start).as_secs_f64()
     );
 }
+
+#[test]
+#[serial]
+#[traced_test]
+fn test_transcribe_hotwords() {
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
+    let options = &TranscribeOptions {
+        lang: Some("en".into()),
+        path: "../samples/short.wav".into(),
+        hotwords: Some(vec!["Kubernetes".into(), "PostgreSQL".into()]),
+        ..Default::default()
+    };
+    assert_eq!(initial_prompt(options).as_deref(), Some("Kubernetes, PostgreSQL."));
+    let result = crate::transcribe::transcribe(&ctx, options, None, None, None, None).unwrap();
+    println!("{:?}", result);
+    assert!(!result.segments.is_empty());
+
+    // Hotwords are decoded as the equivalent initial prompt
+    let prompt_options = &TranscribeOptions {
+        hotwords: None,
+        init_prompt: Some("Kubernetes, PostgreSQL.".into()),
+        ..options.clone()
+    };
+    let prompt_result = crate::transcribe::transcribe(&ctx, prompt_options, None, None, None, None).unwrap();
+    assert_eq!(result.as_text(), prompt_result.as_text());
+}
+
+#[test]
+#[serial]
+#[traced_test]
+fn test_transcribe_raw_audio() {
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
+    let options = &TranscribeOptions {
+        lang: Some("en".into()),
+        ..Default::default()
+    };
+    let pcm = crate::audio::parse_wav_file(&PathBuf::from("../samples/short.wav")).unwrap();
+    let mut samples = vec![0.0f32; pcm.len()];
+    whisper_rs::convert_integer_to_float_audio(&pcm, &mut samples).unwrap();
+    let result = crate::transcribe::transcribe_raw_audio(&ctx, options, &samples).unwrap();
+    println!("{:?}", result);
+    assert!(!result.segments.is_empty());
+    assert!(crate::transcribe::transcribe_raw_audio(&ctx, options, &[]).is_err());
+
+    // Same validation as transcribe
+    let options = &TranscribeOptions {
+        timeout_action: Some("retry".into()),
+        ..options.clone()
+    };
+    let result = crate::transcribe::transcribe_raw_audio(&ctx, options, &samples);
+    assert!(matches!(result, Err(TranscribeError::InvalidOptions { field, .. }) if field == "timeout_action"));
+}
+
+#[test]
+#[serial]
+#[traced_test]
+fn test_tokenize_only() {
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
+    let options = &TranscribeOptions {
+        init_prompt: Some("Hello world".into()),
+        tokenize_only: Some(true),
+        ..Default::default()
+    };
+    let result = crate::transcribe::transcribe(&ctx, options, None, None, None, None).unwrap();
+    let tokens = result.tokens.unwrap();
+    assert_eq!(result.token_count, Some(tokens.len()));
+    assert!(result.segments.is_empty());
+    let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+    assert_eq!(text, "Hello world");
+}
+
+#[test]
+#[serial]
+#[traced_test]
+fn test_timeout_action() {
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
+    let mut options = TranscribeOptions {
+        path: "../samples/short.wav".into(),
+        lang: Some("en".into()),
+        timeout_secs: Some(0.001),
+        timeout_action: Some("retry".into()),
+        ..Default::default()
+    };
+    let result = crate::transcribe::transcribe(&ctx, &options, None, None, None, None);
+    assert!(matches!(result, Err(TranscribeError::InvalidOptions { field, .. }) if field == "timeout_action"));
+
+    // Doesn't overflow the deadline
+    options.timeout_action = None;
+    for timeout_secs in [0.0, -1.0, 1e20, f32::INFINITY, f32::NAN] {
+        options.timeout_secs = Some(timeout_secs);
+        let result = crate::transcribe::transcribe(&ctx, &options, None, None, None, None);
+        assert!(matches!(result, Err(TranscribeError::InvalidOptions { field, .. }) if field == "timeout_secs"));
+    }
+
+    // Times out before the first segment
+    options.timeout_secs = Some(0.001);
+    options.timeout_action = Some("partial".into());
+    let result = crate::transcribe::transcribe(&ctx, &options, None, None, None, None);
+    assert!(matches!(result, Err(TranscribeError::Timeout)));
+}
+
+#[test]
+#[serial]
+#[traced_test]
+fn test_token_level_timestamps() {
+    let ctx = create_context(&PathBuf::from("../ggml-tiny.bin"), ContextOptions::default()).unwrap();
+    let options = &TranscribeOptions {
+        path: "../samples/short.wav".into(),
+        lang: Some("en".into()),
+        token_level_timestamps: Some(true),
+        ..Default::default()
+    };
+    let result = crate::transcribe::transcribe(&ctx, options, None, None, None, None).unwrap();
+    for segment in result.segments {
+        let tokens = segment.tokens.unwrap();
+        assert!(!tokens.is_empty());
+        let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(text, segment.text);
+        for token in tokens {
+            assert!(token.start.unwrap() <= token.end.unwrap());
+            assert!((0.0..=1.0).contains(&token.p.unwrap()));
+        }
+    }
+}
+
+#[test]
+fn test_read_prompt_file() {
+    let path = std::env::temp_dir().join("vibe_test_prompt.txt");
+    std::fs::write(&path, "Myocardial infarction, troponin.\n").unwrap();
+    assert_eq!(read_prompt_file(&path).unwrap(), "Myocardial infarction, troponin.");
+    // Cached
+    assert_eq!(read_prompt_file(&path).unwrap(), "Myocardial infarction, troponin.");
+    std::fs::remove_file(&path).unwrap();
+    assert!(read_prompt_file(&path).is_err());
+}
+
+#[test]
+fn test_initial_prompt_hotwords() {
+    let mut options = TranscribeOptions {
+        hotwords: Some(vec!["Kubernetes".into(), " ".into(), "PostgreSQL".into()]),
+        ..Default::default()
+    };
+    assert_eq!(initial_prompt(&options).as_deref(), Some("Kubernetes, PostgreSQL."));
+    options.init_prompt = Some("Hello.".into());
+    assert_eq!(initial_prompt(&options).as_deref(), Some("Kubernetes, PostgreSQL. Hello."));
+    options.hotwords = None;
+    assert_eq!(initial_prompt(&options).as_deref(), Some("Hello."));
+}
+
+#[test]
+fn test_split_long_segments() {
+    let segments = vec![
+        Segment {
+            start: 0,
+            stop: 1000,
+            text: " one two three four five six seven eight nine ten".into(),
+            speaker: Some("1".into()),
+            ..Default::default()
+        },
+        Segment {
+            start: 1000,
+            stop: 1200,
+            text: " short".into(),
+            ..Default::default()
+        },
+    ];
+    let result = split_long_segments(segments, 300);
+    assert!(result.len() > 2);
+    assert_eq!(result.first().unwrap().start, 0);
+    assert_eq!(result[result.len() - 2].stop, 1000);
+    assert_eq!(result.last().unwrap().text, " short");
+    for segment in &result[..result.len() - 1] {
+        assert!(segment.stop - segment.start <= 300);
+        assert_eq!(segment.speaker.as_deref(), Some("1"));
+    }
+    let text: Vec<&str> = result[..result.len() - 1].iter().map(|s| s.text.trim()).collect();
+    assert_eq!(text.join(" "), "one two three four five six seven eight nine ten");
+}
+
+#[test]
+fn test_merge_short_segments() {
+    let segment = |start: i64, stop: i64, text: &str| Segment {
+        start,
+        stop,
+        text: text.into(),
+        ..Default::default()
+    };
+    let segments = vec![
+        segment(0, 5, " Oh"),
+        segment(10, 300, " hello there"),
+        segment(300, 305, " friend"),
+        segment(500, 800, " how are you"),
+    ];
+    let result = merge_short_segments(segments, 50);
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].start, 0);
+    assert_eq!(result[0].stop, 305);
+    assert_eq!(result[0].text, " Oh hello there friend");
+    assert_eq!(result[1].text, " how are you");
+}
+
+#[test]
+fn test_split_sentences() {
+    let segments = vec![
+        Segment {
+            start: 0,
+            stop: 300,
+            text: " Hello there. How are".into(),
+            ..Default::default()
+        },
+        Segment {
+            start: 300,
+            stop: 600,
+            text: " you today? I am fine".into(),
+            ..Default::default()
+        },
+        Segment {
+            start: 600,
+            stop: 800,
+            text: " thanks!".into(),
+            ..Default::default()
+        },
+    ];
+    let result = split_sentences(segments);
+    let text: Vec<&str> = result.iter().map(|s| s.text.trim()).collect();
+    assert_eq!(text, vec!["Hello there.", "How are you today?", "I am fine thanks!"]);
+    for segment in &result {
+        assert!(segment.text.trim().ends_with(['.', '?', '!']));
+        assert!(segment.start < segment.stop);
+    }
+    assert_eq!(result.first().unwrap().start, 0);
+    assert_eq!(result.last().unwrap().stop, 800);
+    assert!(result[1].start < 300 && result[1].stop > 300);
+}
+
+#[test]
+fn test_merge_transcripts() {
+    let part = |segments: Vec<(i64, i64, &str)>| Transcript {
+        segments: segments
+            .into_iter()
+            .map(|(start, stop, text)| Segment {
+                start,
+                stop,
+                text: text.into(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let transcripts = vec![
+        part(vec![(0, 500, " first"), (500, 1000, " second")]),
+        part(vec![(0, 400, " third")]),
+    ];
+    let merged = merge_transcripts(transcripts.clone(), &[0.0, 10.0]).unwrap();
+    let starts: Vec<i64> = merged.transcript.segments.iter().map(|s| s.start).collect();
+    assert_eq!(starts, vec![0, 500, 1000]);
+    assert_eq!(merged.transcript.segments[2].text, " third");
+    assert!(merged.warnings.is_empty());
+
+    // Second part starts before the first one ends
+    let merged = merge_transcripts(transcripts.clone(), &[0.0, 8.0]).unwrap();
+    let texts: Vec<&str> = merged.transcript.segments.iter().map(|s| s.text.trim()).collect();
+    assert_eq!(texts, vec!["first", "second", "third"]);
+    assert_eq!(merged.warnings.len(), 1);
+
+    assert!(merge_transcripts(transcripts, &[0.0, 1.0, 2.0]).is_err());
+}
+
+#[test]
+fn test_truncate_transcript() {
+    let mut transcript = Transcript {
+        segments: (0..5)
+            .map(|i| Segment {
+                start: i * 100,
+                stop: (i + 1) * 100,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    transcript.truncate(10);
+    assert_eq!(transcript.truncated, None);
+    assert_eq!(transcript.total_segment_count, Some(5));
+
+    transcript.truncate(2);
+    assert_eq!(transcript.segments.len(), 2);
+    assert_eq!(transcript.truncated, Some(true));
+    assert_eq!(transcript.truncated_at_secs, Some(2.0));
+    assert_eq!(transcript.total_segment_count, Some(5));
+}
+
+#[test]
+fn test_auto_punctuate() {
+    let mut segments: Vec<Segment> = [" hello world", " is it? yes it is,", " Done!", ""]
+        .iter()
+        .map(|text| Segment {
+            text: text.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    auto_punctuate(&mut segments);
+    let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+    assert_eq!(texts, vec![" Hello world.", " Is it? Yes it is.", " Done!", ""]);
+}
+
+#[test]
+fn test_filter_profanity() {
+    let mut segments = vec![Segment {
+        text: " Damn, that's a damned DAMN mess".into(),
+        ..Default::default()
+    }];
+    let filtered = filter_profanity(&mut segments, &["damn".to_string()], "****");
+    assert_eq!(filtered, 2);
+    assert_eq!(segments[0].text, " ****, that's a damned **** mess");
+}
+
+#[test]
+fn test_mask_pii() {
+    let mut segments = vec![Segment {
+        text: " Call 555-123-4567 or (555) 987-6543, mail John.Doe@example.com. SSN 123-45-6789, card 4111 1111 1111 1111, order 1234 5678 9012 3456, badge EMP-0042".into(),
+        ..Default::default()
+    }];
+    let custom = compile_patterns(&[PiiPattern {
+        name: "employee_id".into(),
+        pattern: r"\bEMP-\d{4}\b".into(),
+    }])
+    .unwrap();
+    let counts = mask_pii(&mut segments, &custom);
+    assert_eq!(
+        segments[0].text,
+        " Call [PHONE] or [PHONE], mail [EMAIL]. SSN [SSN], card [CREDIT_CARD], order 1234 5678 9012 3456, badge [EMPLOYEE_ID]"
+    );
+    let counts: Vec<(&str, usize)> = counts.iter().map(|count| (count.entity_type.as_str(), count.count)).collect();
+    assert_eq!(
+        counts,
+        vec![("EMAIL", 1), ("SSN", 1), ("CREDIT_CARD", 1), ("PHONE", 2), ("EMPLOYEE_ID", 1)]
+    );
+    assert!(compile_patterns(&[PiiPattern {
+        name: "broken".into(),
+        pattern: "(".into(),
+    }])
+    .is_err());
+}
+
+#[test]
+fn test_transcript_confidence() {
+    let mut transcript = Transcript {
+        segments: [(0, 100, 0.9), (100, 400, 0.5), (400, 500, 0.2)]
+            .iter()
+            .map(|(start, stop, confidence)| Segment {
+                start: *start,
+                stop: *stop,
+                confidence: Some(*confidence),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    transcript.compute_confidence();
+    let confidence = transcript.transcript_confidence.unwrap();
+    assert!((confidence - 0.52).abs() < 1e-5);
+    assert_eq!(transcript.confidence_quartiles, Some(vec![0.2, 0.5, 0.9, 0.9]));
+    assert_eq!(transcript.low_confidence_segment_count, Some(1));
+}
+
+#[test]
+fn test_split_channels() {
+    let path = std::env::temp_dir().join("vibe_test_stereo.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for _ in 0..16000 {
+        writer.write_sample(1000i16).unwrap();
+        writer.write_sample(-1000i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let channels = crate::audio::split_channels(&path).unwrap();
+    assert_eq!(channels.len(), 2);
+    for (channel, expected) in channels.iter().zip([1000i16, -1000]) {
+        let mut reader = hound::WavReader::open(channel).unwrap();
+        assert_eq!(reader.spec().channels, 1);
+        assert!(reader.samples::<i16>().all(|sample| sample.unwrap() == expected));
+        std::fs::remove_file(channel).unwrap();
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_wav_duration_malformed_header() {
+    let path = std::env::temp_dir().join("vibe_test_malformed.wav");
+    let wav = |fmt_size: u32, data_size: u32| {
+        let mut header = Vec::new();
+        header.extend_from_slice(b"RIFF\x24\x00\x00\x00WAVEfmt ");
+        header.extend_from_slice(&fmt_size.to_le_bytes());
+        // PCM, mono, 16KHz, 32000 bytes per second
+        header.extend_from_slice(&[1, 0, 1, 0, 0x80, 0x3e, 0, 0, 0x00, 0x7d, 0, 0, 2, 0, 16, 0]);
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+        header
+    };
+    std::fs::write(&path, wav(16, 32000)).unwrap();
+    assert_eq!(crate::audio::wav_duration(&path).unwrap(), Some(1.0));
+    // Huge fmt chunk isn't allocated
+    std::fs::write(&path, wav(u32::MAX, 32000)).unwrap();
+    assert_eq!(crate::audio::wav_duration(&path).unwrap(), None);
+    // Streamed WAV without size
+    std::fs::write(&path, wav(16, 0)).unwrap();
+    assert_eq!(crate::audio::wav_duration(&path).unwrap(), None);
+    std::fs::write(&path, wav(16, u32::MAX)).unwrap();
+    assert_eq!(crate::audio::wav_duration(&path).unwrap(), None);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_detect_format_adts() {
+    let path = std::env::temp_dir().join("vibe_test_detect_format");
+    // ADTS AAC (MPEG-4 and MPEG-2) and MP3 frame headers
+    for (header, format) in [
+        ([0xFF, 0xF1, 0x50, 0x80], "aac"),
+        ([0xFF, 0xF9, 0x50, 0x80], "aac"),
+        ([0xFF, 0xFB, 0x90, 0x64], "mp3"),
+    ] {
+        std::fs::write(&path, header).unwrap();
+        assert_eq!(crate::audio::detect_format(&path).unwrap(), Some(format));
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_probe_audio() {
+    let path = std::env::temp_dir().join("vibe_test_probe.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for _ in 0..44100 * 2 {
+        writer.write_sample(0i16).unwrap();
+    }
+    writer.finalize().unwrap();
+    let info = crate::audio::probe_audio(&path).unwrap();
+    assert_eq!((info.sample_rate, info.channels), (44100, 2));
+    assert!((info.duration_secs - 1.0).abs() < 0.01);
+    std::fs::remove_file(path).unwrap();
+
+    let stderr = "Input #0, mp3, from 'a.mp3':\n  Duration: 00:00:05.00, start: 0.025057, bitrate: 128 kb/s\n  Stream #0:0: Audio: mp3, 44100 Hz, stereo, fltp, 128 kb/s";
+    assert_eq!(crate::audio::parse_ffmpeg_audio_stream(stderr), Some((44100, 2)));
+    let stderr = "  Stream #0:1(und): Audio: aac (LC) (mp4a / 0x6134706D), 48000 Hz, 5.1, fltp, 384 kb/s";
+    assert_eq!(crate::audio::parse_ffmpeg_audio_stream(stderr), Some((48000, 6)));
+    let stderr = "  Stream #0:0: Audio: pcm_s16le ([1][0][0][0] / 0x0001), 8000 Hz, 3 channels, s16, 384 kb/s";
+    assert_eq!(crate::audio::parse_ffmpeg_audio_stream(stderr), Some((8000, 3)));
+    assert_eq!(crate::audio::parse_ffmpeg_audio_stream("Stream #0:0: Video: h264"), None);
+}
+
+#[test]
+fn test_whisper_cpp_json() {
+    let transcript = Transcript {
+        segments: vec![
+            Segment {
+                start: 0,
+                stop: 230,
+                text: " Hello world.".into(),
+                ..Default::default()
+            },
+            Segment {
+                start: 230,
+                stop: 410,
+                text: " Bye.".into(),
+                no_speech_prob: Some(0.1),
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let options = TranscribeOptions {
+        lang: Some("en".into()),
+        ..Default::default()
+    };
+    let value = crate::formats::whisper_cpp_json(&transcript, &options, false);
+    assert_eq!(value["text"], " Hello world. Bye.");
+    assert!(value.get("task").is_none());
+    let segment = &value["segments"][1];
+    assert_eq!(segment["id"], 1);
+    assert_eq!(segment["seek"], 230);
+    assert_eq!(segment["start"], 2.3);
+    assert_eq!(segment["end"], 4.1);
+    assert_eq!(segment["text"], " Bye.");
+    assert_eq!(segment["tokens"], serde_json::json!([]));
+    assert_eq!(segment["temperature"], 0.0);
+
+    let value = crate::formats::whisper_cpp_json(&transcript, &options, true);
+    assert_eq!(value["task"], "transcribe");
+    assert_eq!(value["language"], "en");
+    assert_eq!(value["duration"], 4.1);
+    assert!(value.get("words").is_none());
+
+    // word_timestamps form field
+    let options = TranscribeOptions {
+        word_timestamps: Some(true),
+        ..options
+    };
+    let value = crate::formats::whisper_cpp_json(&transcript, &options, true);
+    let word = &value["words"][1];
+    assert_eq!(word["word"], "Bye.");
+    assert_eq!(word["start"], 2.3);
+    assert_eq!(word["end"], 4.1);
+}
+
+#[test]
+fn test_parse_srt_invalid_timestamps() {
+    let cue = |timing: &str| format!("1\n{}\nHello\n", timing);
+    let transcript = crate::formats::parse_srt(&cue("00:00:01,5 --> 01:02,250")).unwrap();
+    assert_eq!((transcript.segments[0].start, transcript.segments[0].stop), (150, 6225));
+    for timing in [
+        "-1:00:00,000 --> 00:00:01,000",
+        "00:00:00,000 --> 00:-1:01,000",
+        "00:00:01,ab€ --> 00:00:02,000",
+        "00:00:01,€ --> 00:00:02,000",
+        "00:00:+1,000 --> 00:00:02,000",
+        "99999999999999999999:00:00,000 --> 00:00:02,000",
+    ] {
+        assert!(crate::formats::parse_srt(&cue(timing)).is_err(), "{}", timing);
+    }
+}
+
+#[test]
+fn test_convert_invalid_json_timestamps() {
+    let json = |start: i64, stop: i64| {
+        format!(
+            r#"{{"processing_time_sec": 0, "segments": [{{"start": {}, "stop": {}, "text": " Hello"}}]}}"#,
+            start, stop
+        )
+    };
+    assert!(crate::formats::convert(&json(100, 200), "json", "ass").is_ok());
+    assert!(crate::formats::convert(&json(-100, 200), "json", "ass").is_err());
+    assert!(crate::formats::convert(&json(100, -200), "json", "srt").is_err());
+    assert!(crate::formats::convert(&json(300, 200), "json", "vtt").is_err());
+}
+
+#[test]
+fn test_ass_timestamp() {
+    assert_eq!(crate::transcript::format_ass_timestamp(366_150), "1:01:01.50");
+    assert_eq!(crate::transcript::format_ass_timestamp(-100), "0:00:00.00");
+}
+
+#[test]
+fn test_vtt_style() {
+    let transcript = Transcript {
+        segments: vec![Segment {
+            start: 100,
+            stop: 300,
+            text: " Hello".into(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let style = VttStyle {
+        color: Some("yellow".into()),
+        position: Some(10.0),
+        align: Some("left".into()),
+        ..Default::default()
+    };
+    let vtt = transcript.as_vtt(false, Some(&style));
+    assert!(vtt.starts_with("WEBVTT\n\nSTYLE\n::cue {\n  color: yellow;\n}\n"));
+    assert!(vtt.contains("00:01.000 --> 00:03.000 position:10% align:left\nHello"));
+}
+
+#[test]
+fn test_sentence_timestamps() {
+    let words: Vec<Segment> = [" Hello", " world.", " Mr.", " Smith", " is", " here.", " ok"]
+        .iter()
+        .enumerate()
+        .map(|(i, text)| Segment {
+            start: i as i64 * 100 + 10,
+            stop: i as i64 * 100 + 90,
+            text: text.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    let sentences = sentence_timestamps(words);
+    let result: Vec<(i64, i64, &str)> = sentences.iter().map(|s| (s.start, s.stop, s.text.as_str())).collect();
+    assert_eq!(
+        result,
+        vec![
+            (10, 190, " Hello world."),
+            (210, 290, " Mr."),
+            (310, 690, " Smith is here. ok")
+        ]
+    );
+}
+
+#[test]
+fn test_mark_speaker_changes() {
+    let mut segments: Vec<Segment> = [
+        (0, 200, " Hello there."),
+        (400, 600, " How are you?"),
+        (650, 800, " Fine."),
+        (1000, 1200, " and you"),
+    ]
+    .iter()
+    .map(|(start, stop, text)| Segment {
+        start: *start,
+        stop: *stop,
+        text: text.to_string(),
+        ..Default::default()
+    })
+    .collect();
+    assert_eq!(mark_speaker_changes(&mut segments), 1);
+    let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+    assert_eq!(texts, vec![" Hello there.", " >> How are you?", " Fine.", " and you"]);
+}
+
+#[test]
+fn test_summarize_segments() {
+    let segments: Vec<Segment> = [
+        (0, 300, " The whisper model transcribes audio."),
+        (300, 600, " Whisper runs locally on the laptop."),
+        (600, 800, " Nice weather today."),
+        (800, 1200, " The whisper model supports many languages."),
+    ]
+    .iter()
+    .map(|(start, stop, text)| Segment {
+        start: *start,
+        stop: *stop,
+        text: text.to_string(),
+        ..Default::default()
+    })
+    .collect();
+    let options = SummarizeOptions {
+        max_sentences: Some(2),
+        style: SummaryStyle::Bullet,
+    };
+    let summary = summarize_segments(&segments, &options);
+    assert_eq!(
+        summary.summary,
+        "- The whisper model transcribes audio.\n- The whisper model supports many languages."
+    );
+    assert_eq!(summary.key_topics[..2], ["whisper", "model"]);
+    assert_eq!(summary.duration_summarized_secs, 12.0);
+}
+
+#[test]
+fn test_dtw_model_preset() {
+    assert!(matches!(
+        dtw_model_preset(Path::new("ggml-base.en.bin")),
+        Some(DtwModelPreset::BaseEn)
+    ));
+    assert!(matches!(
+        dtw_model_preset(Path::new("/models/ggml-large-v3-q5_0.bin")),
+        Some(DtwModelPreset::LargeV3)
+    ));
+    assert!(dtw_model_preset(Path::new("ggml-large-v3-turbo.bin")).is_none());
+    assert!(dtw_model_preset(Path::new("custom.bin")).is_none());
+}
+
+#[test]
+fn test_validate_options() {
+    assert!(validate_options(&TranscribeOptions::default(), false).is_empty());
+
+    let options = TranscribeOptions {
+        tdrz: Some(true),
+        sentence_timestamps: Some(true),
+        ..Default::default()
+    };
+    let warnings = validate_options(&options, true);
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().all(|warning| warning.kind == ConfigWarningKind::Conflict));
+    assert_eq!(warnings[0].fields, vec!["tdrz", "diarize"]);
+    assert!(validate_options(&options, false)
+        .iter()
+        .all(|warning| warning.fields[0] == "sentence_timestamps"));
+
+    let options = TranscribeOptions {
+        beam_size: Some(5),
+        channel_names: Some(vec!["left".into(), "right".into()]),
+        ..Default::default()
+    };
+    let warnings = validate_options(&options, false);
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().all(|warning| warning.kind == ConfigWarningKind::Incompatible));
+
+    let options = TranscribeOptions {
+        tdrz: Some(true),
+        lang: Some("de".into()),
+        ..Default::default()
+    };
+    assert_eq!(validate_options(&options, false)[0].kind, ConfigWarningKind::Unsupported);
+}
+
+#[test]
+fn test_validate_max_text_ctx() {
+    let options = |max_text_ctx| TranscribeOptions {
+        max_text_ctx: Some(max_text_ctx),
+        ..Default::default()
+    };
+    assert!(validate_options(&options(-1), false).is_empty());
+    assert!(validate_options(&options(64), false).is_empty());
+    for invalid in [0, -2, i32::MIN] {
+        let warnings = validate_options(&options(invalid), false);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, ConfigWarningKind::Conflict);
+        assert_eq!(warnings[0].fields, vec!["max_text_ctx"]);
+    }
+}
@@ -1,24 +1,88 @@
 use crate::audio;
-use crate::config::TranscribeOptions;
-use crate::transcript::{Segment, Transcript};
-use eyre::{bail, eyre, Context, OptionExt, Result};
+use crate::config::{self, ConfigWarning, ConfigWarningKind, TranscribeOptions};
+use crate::error::TranscribeError;
+use crate::pii_filter;
+use crate::post_process;
+use crate::segment_utils;
+use crate::transcript::{AudioMeta, ChannelTranscript, Preprocessing, Segment, Token, Transcript};
+use eyre::{eyre, Context, OptionExt, Result};
 use hound::WavReader;
+use regex::Regex;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
-use std::time::Instant;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tempfile::TempPath;
 pub use whisper_rs::SegmentCallbackData;
 pub use whisper_rs::WhisperContext;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContextParameters};
+use whisper_rs::{DtwMode, DtwModelPreset, DtwParameters, FullParams, SamplingStrategy, WhisperContextParameters, WhisperState};
 
 type ProgressCallbackType = once_cell::sync::Lazy<Mutex<Option<Box<dyn Fn(i32) + Send + Sync>>>>;
 static PROGRESS_CALLBACK: ProgressCallbackType = once_cell::sync::Lazy::new(|| Mutex::new(None));
+// Prompt files by path with their modification time
+type PromptFileCache = once_cell::sync::Lazy<Mutex<HashMap<PathBuf, (SystemTime, String)>>>;
+static PROMPT_FILE_CACHE: PromptFileCache = once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
-pub fn create_context(model_path: &Path, gpu_device: Option<i32>) -> Result<WhisperContext> {
+/// Options of the whisper context. changing them requires loading the model again
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ContextOptions {
+    pub gpu_device: Option<i32>,
+    pub flash_attn: bool,
+    pub dtw_timestamps: bool,
+}
+
+impl ContextOptions {
+    /// Context options with the ones set explicitly in transcribe options
+    pub fn with_overrides(self, options: &TranscribeOptions) -> Self {
+        Self {
+            gpu_device: options.gpu_device.or(self.gpu_device),
+            flash_attn: options.flash_attn.unwrap_or(self.flash_attn),
+            dtw_timestamps: options.dtw_timestamps.unwrap_or(self.dtw_timestamps),
+        }
+    }
+}
+
+/// Alignment heads of the model for DTW timestamps, picked by the model file name (eg. ggml-base.en.bin).
+/// Quantized models use the heads of the original model
+pub(crate) fn dtw_model_preset(model_path: &Path) -> Option<DtwModelPreset> {
+    let name = model_path.file_stem()?.to_str()?.to_lowercase();
+    let name = name.trim_start_matches("ggml-");
+    // Turbo has other heads than large-v3
+    if name.contains("turbo") {
+        return None;
+    }
+    let presets = [
+        ("large-v3", DtwModelPreset::LargeV3),
+        ("large-v2", DtwModelPreset::LargeV2),
+        ("large-v1", DtwModelPreset::LargeV1),
+        ("medium.en", DtwModelPreset::MediumEn),
+        ("medium", DtwModelPreset::Medium),
+        ("small.en", DtwModelPreset::SmallEn),
+        ("small", DtwModelPreset::Small),
+        ("base.en", DtwModelPreset::BaseEn),
+        ("base", DtwModelPreset::Base),
+        ("tiny.en", DtwModelPreset::TinyEn),
+        ("tiny", DtwModelPreset::Tiny),
+    ];
+    presets
+        .into_iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|(_, preset)| preset)
+}
+
+pub fn create_context(model_path: &Path, options: ContextOptions) -> Result<WhisperContext, TranscribeError> {
     whisper_rs::install_whisper_tracing_trampoline();
     tracing::debug!("open model...");
+    let model_load_failed = |source: eyre::Report| TranscribeError::ModelLoadFailed {
+        path: model_path.to_path_buf(),
+        source: source.into(),
+    };
     if !model_path.exists() {
-        bail!("whisper file doesn't exist")
+        return Err(model_load_failed(eyre!("whisper file doesn't exist")));
     }
     let mut ctx_params = WhisperContextParameters::default();
     if !env!("CUDA_VERSION").is_empty() || !env!("ROCM_VERSION").is_empty() {
@@ -26,22 +90,37 @@ pub fn create_context(model_path: &Path, gpu_device: Option<i32>) -> Result<Whis
         ctx_params.use_gpu = true;
     }
     // set GPU device number from preference
-    if let Some(gpu_device) = gpu_device {
+    if let Some(gpu_device) = options.gpu_device {
         ctx_params.gpu_device = gpu_device;
     }
+    ctx_params.flash_attn = options.flash_attn;
+    if options.dtw_timestamps {
+        let model_preset = dtw_model_preset(model_path).ok_or_else(|| {
+            model_load_failed(eyre!(
+                "dtw_timestamps requires an official whisper model with known alignment heads (eg. ggml-base.en.bin)"
+            ))
+        })?;
+        ctx_params.dtw_parameters = DtwParameters {
+            mode: DtwMode::ModelPreset { model_preset },
+            ..Default::default()
+        };
+    }
     tracing::debug!("gpu device: {:?}", ctx_params.gpu_device);
     tracing::debug!("use gpu: {:?}", ctx_params.use_gpu);
-    let model_path = model_path.to_str().ok_or_eyre("can't convert model option to str")?;
-    tracing::debug!("creating whisper context with model path {}", model_path);
+    tracing::debug!("flash attention: {:?}", ctx_params.flash_attn);
+    tracing::debug!("dtw timestamps: {:?}", options.dtw_timestamps);
+    let model_path_str = model_path
+        .to_str()
+        .ok_or_eyre("can't convert model option to str")
+        .map_err(model_load_failed)?;
+    tracing::debug!("creating whisper context with model path {}", model_path_str);
     let ctx_unwind_result = catch_unwind(AssertUnwindSafe(|| {
-        WhisperContext::new_with_params(model_path, ctx_params).context("failed to open model")
+        WhisperContext::new_with_params(model_path_str, ctx_params).context("failed to open model")
     }));
     match ctx_unwind_result {
-        Err(error) => {
-            bail!("create whisper context crash: {:?}", error)
-        }
+        Err(error) => Err(model_load_failed(eyre!("create whisper context crash: {:?}", error))),
         Ok(ctx_result) => {
-            let ctx = ctx_result?;
+            let ctx = ctx_result.map_err(model_load_failed)?;
             tracing::debug!("created context successfuly");
             Ok(ctx)
         }
@@ -62,20 +141,43 @@ pub fn should_normalize(source: PathBuf) -> bool {
     true
 }
 
-pub fn create_normalized_audio(source: PathBuf) -> Result<PathBuf> {
+/// Convert audio to whisper format in temp file. the file is removed when the returned path is dropped
+pub fn create_normalized_audio(
+    source: PathBuf,
+    convert_options: &audio::ConvertOptions,
+) -> Result<(TempPath, Option<audio::Loudness>)> {
     tracing::debug!("normalize {:?}", source.display());
 
-    let out_path = tempfile::Builder::new()
-        .suffix(".wav")
-        .tempfile()?
-        .into_temp_path()
-        .to_path_buf();
-    audio::normalize(source, out_path.clone())?;
-    Ok(out_path)
+    let out_path = tempfile::Builder::new().suffix(".wav").tempfile()?.into_temp_path();
+    let loudness = audio::convert_to_whisper_wav(&source, &out_path, convert_options)?;
+    Ok((out_path, loudness))
 }
 
-fn setup_params(options: &TranscribeOptions) -> FullParams {
-    let mut params = FullParams::new(SamplingStrategy::default());
+const DEFAULT_BEAM_SIZE: i32 = 5;
+
+fn sampling_strategy(options: &TranscribeOptions) -> Result<SamplingStrategy, TranscribeError> {
+    match options.sampling_strategy.as_deref() {
+        None | Some("greedy") => Ok(SamplingStrategy::default()),
+        Some("beam_search") => {
+            let beam_size = options.beam_size.unwrap_or(DEFAULT_BEAM_SIZE);
+            if beam_size < 1 {
+                return Err(TranscribeError::invalid_options("beam_size", "must be at least 1"));
+            }
+            Ok(SamplingStrategy::BeamSearch {
+                beam_size,
+                patience: options.patience.unwrap_or(1.0),
+            })
+        }
+        Some(strategy) => Err(TranscribeError::invalid_options(
+            "sampling_strategy",
+            format!("unknown strategy {}. use greedy or beam_search", strategy),
+        )),
+    }
+}
+
+fn setup_params(options: &TranscribeOptions, strategy: SamplingStrategy) -> FullParams {
+    tracing::debug!("sampling strategy {:?}", options.sampling_strategy);
+    let mut params = FullParams::new(strategy);
     tracing::debug!("set language to {:?}", options.lang);
 
     if let Some(true) = options.word_timestamps {
@@ -87,6 +189,9 @@ fn setup_params(options: &TranscribeOptions) -> FullParams {
     if let Some(true) = options.translate {
         params.set_translate(true);
     }
+    if let Some(true) = options.tdrz {
+        params.set_tdrz_enable(true);
+    }
     if options.lang.is_some() {
         params.set_language(options.lang.as_deref());
     }
@@ -109,7 +214,7 @@ fn setup_params(options: &TranscribeOptions) -> FullParams {
     }
 
     // handle args
-    if let Some(init_prompt) = options.init_prompt.to_owned() {
+    if let Some(init_prompt) = initial_prompt(options) {
         tracing::debug!("setting init prompt to {init_prompt}");
         params.set_initial_prompt(&init_prompt);
     }
@@ -121,6 +226,230 @@ fn setup_params(options: &TranscribeOptions) -> FullParams {
     params
 }
 
+/// Contents of the prompt file. cached until the file modification time changes
+pub(crate) fn read_prompt_file(path: &Path) -> Result<String, TranscribeError> {
+    let read_error = |e: std::io::Error| {
+        TranscribeError::invalid_options("initial_prompt_file", format!("can't read {}: {}", path.display(), e))
+    };
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(read_error)?;
+    let mut cache = PROMPT_FILE_CACHE.lock().map_err(|e| eyre!("{:?}", e))?;
+    if let Some((cached_modified, prompt)) = cache.get(path) {
+        if *cached_modified == modified {
+            return Ok(prompt.clone());
+        }
+    }
+    let prompt = std::fs::read_to_string(path).map_err(read_error)?.trim().to_string();
+    tracing::debug!("read prompt file {} ({} chars)", path.display(), prompt.len());
+    cache.insert(path.to_path_buf(), (modified, prompt.clone()));
+    Ok(prompt)
+}
+
+/// Options with `init_prompt` read from `initial_prompt_file`
+fn resolve_prompt_file(options: &TranscribeOptions) -> Result<Cow<'_, TranscribeOptions>, TranscribeError> {
+    let Some(path) = options.initial_prompt_file.as_deref() else {
+        return Ok(Cow::Borrowed(options));
+    };
+    let mut options = options.clone();
+    options.init_prompt = Some(read_prompt_file(Path::new(path))?);
+    Ok(Cow::Owned(options))
+}
+
+/// Initial prompt with the hotwords prepended
+pub(crate) fn initial_prompt(options: &TranscribeOptions) -> Option<String> {
+    let hotwords: Vec<&str> = options
+        .hotwords
+        .iter()
+        .flatten()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if hotwords.is_empty() {
+        return options.init_prompt.clone();
+    }
+    let hotwords = format!("{}.", hotwords.join(", "));
+    match options.init_prompt.as_deref() {
+        Some(init_prompt) if !init_prompt.is_empty() => Some(format!("{} {}", hotwords, init_prompt)),
+        _ => Some(hotwords),
+    }
+}
+
+/// Tokenize text with the model vocabulary
+pub fn tokenize(ctx: &WhisperContext, text: &str) -> Result<Vec<Token>, TranscribeError> {
+    // Every token is at least one byte
+    let ids = ctx.tokenize(text, text.len() + 1).context("failed to tokenize")?;
+    let tokens = ids
+        .into_iter()
+        .map(|id| {
+            let text = ctx.token_to_str_lossy(id).context("failed to get token text")?;
+            Ok(Token {
+                text: text.to_string(),
+                id,
+                start: None,
+                end: None,
+                p: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(tokens)
+}
+
+/// Segment text without the suppressed tokens
+fn get_segment_text(ctx: &WhisperContext, state: &WhisperState, segment: i32, suppress_tokens: &[i32]) -> Result<String> {
+    if suppress_tokens.is_empty() {
+        return state.full_get_segment_text_lossy(segment).context("failed to get segment");
+    }
+    let mut text = String::new();
+    let n_tokens = state.full_n_tokens(segment).context("failed to get number of tokens")?;
+    for t in 0..n_tokens {
+        let id = state.full_get_token_id(segment, t).context("failed to get token id")?;
+        // Special tokens such as timestamps are not part of the text
+        if id >= ctx.token_eot() || suppress_tokens.contains(&id) {
+            continue;
+        }
+        text += &state
+            .full_get_token_text_lossy(segment, t)
+            .context("failed to get token text")?;
+    }
+    Ok(text)
+}
+
+/// Mean probability of the segment text tokens
+fn get_segment_confidence(ctx: &WhisperContext, state: &WhisperState, segment: i32) -> Result<Option<f32>> {
+    let n_tokens = state.full_n_tokens(segment).context("failed to get number of tokens")?;
+    let mut probs = Vec::new();
+    for t in 0..n_tokens {
+        let id = state.full_get_token_id(segment, t).context("failed to get token id")?;
+        if id >= ctx.token_eot() {
+            continue;
+        }
+        probs.push(state.full_get_token_prob(segment, t).context("failed to get token prob")?);
+    }
+    if probs.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(probs.iter().sum::<f32>() / probs.len() as f32))
+}
+
+/// DTW aligned time of the first text token. None when the context was created without DTW
+fn get_segment_dtw_start(ctx: &WhisperContext, state: &WhisperState, segment: i32) -> Result<Option<i64>> {
+    let n_tokens = state.full_n_tokens(segment).context("failed to get number of tokens")?;
+    for t in 0..n_tokens {
+        let data = state.full_get_token_data(segment, t).context("failed to get token data")?;
+        if data.id >= ctx.token_eot() {
+            continue;
+        }
+        return Ok((data.t_dtw >= 0).then_some(data.t_dtw));
+    }
+    Ok(None)
+}
+
+/// Text tokens of the segment with their timestamps in seconds
+fn get_segment_tokens(ctx: &WhisperContext, state: &WhisperState, segment: i32, suppress_tokens: &[i32]) -> Result<Vec<Token>> {
+    let n_tokens = state.full_n_tokens(segment).context("failed to get number of tokens")?;
+    let mut tokens = Vec::new();
+    for t in 0..n_tokens {
+        let data = state.full_get_token_data(segment, t).context("failed to get token data")?;
+        if data.id >= ctx.token_eot() || suppress_tokens.contains(&data.id) {
+            continue;
+        }
+        let text = state
+            .full_get_token_text_lossy(segment, t)
+            .context("failed to get token text")?;
+        tokens.push(Token {
+            text,
+            id: data.id,
+            start: Some(data.t0 as f32 / 100.0),
+            end: Some(data.t1 as f32 / 100.0),
+            p: Some(data.p),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Read segment from whisper state. with `dtw` the start is taken from the DTW alignment
+fn read_segment(
+    ctx: &WhisperContext,
+    state: &WhisperState,
+    segment: i32,
+    suppress_tokens: &[i32],
+    dtw: bool,
+    token_timestamps: bool,
+) -> Result<Segment> {
+    let mut start = state.full_get_segment_t0(segment).context("failed to get start timestamp")?;
+    let stop = state.full_get_segment_t1(segment).context("failed to get end timestamp")?;
+    if dtw {
+        if let Some(dtw_start) = get_segment_dtw_start(ctx, state, segment)? {
+            start = dtw_start.min(stop);
+        }
+    }
+    Ok(Segment {
+        text: get_segment_text(ctx, state, segment, suppress_tokens)?,
+        start,
+        stop,
+        no_speech_prob: Some(
+            state
+                .full_get_segment_no_speech_prob(segment)
+                .context("failed to get no speech prob")?,
+        ),
+        confidence: get_segment_confidence(ctx, state, segment)?,
+        tokens: match token_timestamps {
+            true => Some(get_segment_tokens(ctx, state, segment, suppress_tokens)?),
+            false => None,
+        },
+        ..Default::default()
+    })
+}
+
+/// Segments with higher no speech probability and lower confidence are likely hallucinations
+const HALLUCINATION_NO_SPEECH_PROB: f32 = 0.6;
+const HALLUCINATION_CONFIDENCE: f32 = 0.3;
+
+struct SegmentFilter {
+    no_speech_threshold: Option<f32>,
+    hallucination_filter: bool,
+    remove_hallucinations: bool,
+    hallucination_count: usize,
+}
+
+impl SegmentFilter {
+    fn new(options: &TranscribeOptions) -> Self {
+        let hallucination_filter = options.hallucination_filter.unwrap_or_default();
+        // Hallucinations are detected by no speech probability too, don't drop them before
+        let default_no_speech_threshold = if hallucination_filter {
+            None
+        } else {
+            Some(DEFAULT_NO_SPEECH_THRESHOLD)
+        };
+        Self {
+            no_speech_threshold: options.no_speech_threshold.or(default_no_speech_threshold),
+            hallucination_filter,
+            remove_hallucinations: options.remove_hallucinations.unwrap_or_default(),
+            hallucination_count: 0,
+        }
+    }
+
+    /// Set hallucination risk and return whether to keep the segment
+    fn keep(&mut self, segment: &mut Segment) -> bool {
+        let no_speech_prob = segment.no_speech_prob.unwrap_or_default();
+        if self.no_speech_threshold.is_some_and(|threshold| no_speech_prob > threshold) {
+            tracing::debug!("skip segment with no speech prob {}: {}", no_speech_prob, segment.text);
+            return false;
+        }
+        if self.hallucination_filter {
+            let confidence = segment.confidence.unwrap_or(1.0);
+            segment.hallucination_risk = Some(((no_speech_prob + (1.0 - confidence)) / 2.0).clamp(0.0, 1.0));
+            if no_speech_prob > HALLUCINATION_NO_SPEECH_PROB && confidence < HALLUCINATION_CONFIDENCE {
+                tracing::debug!("possible hallucination: {}", segment.text);
+                self.hallucination_count += 1;
+                return !self.remove_hallucinations;
+            }
+        }
+        true
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DiarizeOptions {
     pub segment_model_path: String,
@@ -129,6 +458,335 @@ pub struct DiarizeOptions {
     pub max_speakers: usize,
 }
 
+const DEFAULT_NO_SPEECH_THRESHOLD: f32 = 0.6;
+const DEFAULT_NORMALIZE_TARGET_LUFS: f32 = -23.0;
+const DEFAULT_PROFANITY_REPLACEMENT: &str = "****";
+const MIN_AUDIO_GAIN_DB: f32 = -20.0;
+const MAX_AUDIO_GAIN_DB: f32 = 40.0;
+
+/// Clamped gain or None when there's nothing to apply
+fn audio_gain_db(options: &TranscribeOptions) -> Option<f32> {
+    options
+        .audio_gain_db
+        .map(|gain_db| gain_db.clamp(MIN_AUDIO_GAIN_DB, MAX_AUDIO_GAIN_DB))
+        .filter(|gain_db| *gain_db != 0.0)
+}
+
+/// Transcribe every channel on its own and merge the segments sorted by time
+fn transcribe_multichannel(
+    ctx: &WhisperContext,
+    options: &TranscribeOptions,
+    progress_callback: Option<Box<dyn Fn(i32) + Send + Sync>>,
+    new_segment_callback: Option<Box<dyn Fn(Segment)>>,
+    abort_callback: Option<Box<dyn Fn() -> bool>>,
+    deadline: Option<Instant>,
+) -> Result<Transcript, TranscribeError> {
+    // Removed when dropped, including when a channel fails
+    let channel_paths = audio::split_channels(Path::new(&options.path)).map_err(TranscribeError::AudioDecodeError)?;
+    let channels_count = channel_paths.len() as i32;
+    let split_channels = options.split_channels == Some(true);
+    if split_channels && channel_paths.len() < 2 {
+        return Err(TranscribeError::invalid_options(
+            "split_channels",
+            "audio has a single channel",
+        ));
+    }
+    let partial_on_timeout = options.timeout_action.as_deref() == Some("partial");
+    let progress_callback: Option<Arc<dyn Fn(i32) + Send + Sync>> = progress_callback.map(Arc::from);
+    let new_segment_callback: Option<Rc<dyn Fn(Segment)>> = new_segment_callback.map(Rc::from);
+    let abort_callback: Option<Rc<dyn Fn() -> bool>> = abort_callback.map(Rc::from);
+
+    let st = Instant::now();
+    let mut segments = Vec::new();
+    let mut words_filtered = None;
+    let mut pii_entities_found: Option<Vec<pii_filter::PiiEntityCount>> = None;
+    let mut hallucination_count = None;
+    let mut normalization_applied = false;
+    let mut timed_out = false;
+    let mut channels = Vec::new();
+    for (i, path) in channel_paths.iter().enumerate() {
+        let speaker = options
+            .channel_names
+            .as_ref()
+            .and_then(|names| names.get(i).cloned())
+            .unwrap_or(format!("Channel {}", i));
+        tracing::debug!("transcribe channel {} as {}", i, speaker);
+
+        let mut channel_options = options.clone();
+        channel_options.path = path.to_str().context("tostr")?.to_string();
+        channel_options.multichannel = None;
+        channel_options.split_channels = None;
+        channel_options.channel_names = None;
+        channel_options.max_segment_count = None;
+        // All channels share one deadline
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                timed_out = true;
+                break;
+            }
+            channel_options.timeout_secs = Some(remaining.as_secs_f32());
+        }
+
+        let channel_progress_callback = progress_callback.clone().map(|callback| {
+            Box::new(move |progress: i32| callback((i as i32 * 100 + progress) / channels_count))
+                as Box<dyn Fn(i32) + Send + Sync>
+        });
+        let channel_new_segment_callback = new_segment_callback.clone().map(|callback| {
+            let speaker = speaker.clone();
+            Box::new(move |mut segment: Segment| {
+                segment.speaker = Some(speaker.clone());
+                callback(segment)
+            }) as Box<dyn Fn(Segment)>
+        });
+        let channel_abort_callback = abort_callback
+            .clone()
+            .map(|callback| Box::new(move || callback()) as Box<dyn Fn() -> bool>);
+
+        let result = match transcribe(
+            ctx,
+            &channel_options,
+            channel_progress_callback,
+            channel_new_segment_callback,
+            channel_abort_callback,
+            None,
+        ) {
+            Err(TranscribeError::Timeout) => {
+                timed_out = true;
+                break;
+            }
+            result => result?,
+        };
+        if let Some(count) = result.words_filtered {
+            *words_filtered.get_or_insert(0) += count;
+        }
+        if let Some(counts) = &result.pii_entities_found {
+            pii_filter::merge_counts(pii_entities_found.get_or_insert_with(Vec::new), counts);
+        }
+        if let Some(count) = result.hallucination_count {
+            *hallucination_count.get_or_insert(0) += count;
+        }
+        normalization_applied |= result.audio_meta.as_ref().is_some_and(|meta| meta.normalization_applied);
+        let channel_timed_out = result.status.as_deref() == Some("partial");
+        if split_channels {
+            channels.push(ChannelTranscript {
+                channel: i,
+                result: result.clone(),
+            });
+        }
+        segments.extend(result.segments.into_iter().map(|mut segment| {
+            segment.speaker = Some(speaker.clone());
+            segment
+        }));
+        if channel_timed_out {
+            timed_out = true;
+            break;
+        }
+    }
+    if timed_out && !(partial_on_timeout && !segments.is_empty()) {
+        return Err(TranscribeError::Timeout);
+    }
+    segments.sort_by_key(|segment| segment.start);
+
+    let audio_meta = audio::probe_audio(Path::new(&options.path))
+        .map_err(|e| tracing::debug!("failed to probe audio: {:?}", e))
+        .ok()
+        .map(|info| AudioMeta {
+            original_sample_rate: info.sample_rate,
+            original_channels: info.channels,
+            original_duration_secs: info.duration_secs as f32,
+            processed_sample_rate: 16000,
+            processed_channels: 1,
+            processed_duration_secs: info.duration_secs as f32,
+            normalization_applied,
+            ..Default::default()
+        });
+    let mut transcript = Transcript {
+        segments,
+        processing_time_sec: Instant::now().duration_since(st).as_secs(),
+        audio_meta,
+        preprocessing: audio_gain_db(options).map(|gain_db| Preprocessing {
+            gain_applied_db: Some(gain_db),
+        }),
+        hallucination_count,
+        words_filtered,
+        pii_entities_found,
+        channels: split_channels.then_some(channels),
+        warnings: Some(config::validate_options(options, false)).filter(|warnings| !warnings.is_empty()),
+        ..Default::default()
+    };
+    finish_transcript(&mut transcript, options, timed_out);
+    Ok(transcript)
+}
+
+/// Options checked before transcribing. shared by `transcribe` and `transcribe_raw_audio`
+struct CheckedOptions {
+    suppress_tokens: Vec<i32>,
+    warnings: Vec<ConfigWarning>,
+    profanity_list: Option<Vec<String>>,
+    pii_patterns: Option<Vec<(String, Regex)>>,
+    deadline: Option<Instant>,
+    partial_on_timeout: bool,
+}
+
+/// Validate the options which don't depend on the audio source
+fn check_options(ctx: &WhisperContext, options: &TranscribeOptions, diarize: bool) -> Result<CheckedOptions, TranscribeError> {
+    let suppress_tokens = options.suppress_tokens.clone().unwrap_or_default();
+    let n_vocab = ctx.n_vocab();
+    if let Some(token) = suppress_tokens.iter().find(|id| **id < 0 || **id >= n_vocab) {
+        return Err(TranscribeError::invalid_options(
+            "suppress_tokens",
+            format!("suppress token {} is out of the vocabulary range 0..{}", token, n_vocab),
+        ));
+    }
+    if options.max_segment_duration_secs.is_some_and(|max| max <= 0.0) {
+        return Err(TranscribeError::invalid_options(
+            "max_segment_duration_secs",
+            "must be positive",
+        ));
+    }
+
+    let warnings = config::validate_options(options, diarize);
+    if let Some(conflict) = warnings.iter().find(|warning| warning.kind == ConfigWarningKind::Conflict) {
+        return Err(TranscribeError::invalid_options(
+            &conflict.fields[0],
+            conflict.message.clone(),
+        ));
+    }
+    for warning in &warnings {
+        tracing::warn!(
+            "{:?} options {}: {}",
+            warning.kind,
+            warning.fields.join(", "),
+            warning.message
+        );
+    }
+
+    let profanity_list = match options.profanity_filter {
+        Some(true) => Some(
+            post_process::profanity_list(options.profanity_list_path.as_deref().map(Path::new))
+                .map_err(|e| TranscribeError::invalid_options("profanity_list_path", format!("{:?}", e)))?
+                .ok_or_else(|| {
+                    TranscribeError::invalid_options(
+                        "profanity_list_path",
+                        "required when compiled without the profanity-filter feature",
+                    )
+                })?,
+        ),
+        _ => None,
+    };
+    let pii_patterns = match options.mask_pii {
+        Some(true) => Some(
+            pii_filter::compile_patterns(options.pii_patterns.as_deref().unwrap_or_default())
+                .map_err(|e| TranscribeError::invalid_options("pii_patterns", format!("{:?}", e)))?,
+        ),
+        _ => None,
+    };
+    let deadline = match options.timeout_secs {
+        Some(timeout_secs) => Some(
+            Duration::try_from_secs_f32(timeout_secs)
+                .ok()
+                .filter(|timeout| !timeout.is_zero())
+                .and_then(|timeout| Instant::now().checked_add(timeout))
+                .ok_or_else(|| TranscribeError::invalid_options("timeout_secs", "must be positive and finite"))?,
+        ),
+        None => None,
+    };
+    let partial_on_timeout = match options.timeout_action.as_deref() {
+        None | Some("error") => false,
+        Some("partial") => true,
+        Some(action) => {
+            return Err(TranscribeError::invalid_options(
+                "timeout_action",
+                format!("unknown action {}. use error or partial", action),
+            ))
+        }
+    };
+
+    if options.emotion_detection == Some(true) {
+        if cfg!(not(feature = "emotion")) {
+            return Err(TranscribeError::invalid_options(
+                "emotion_detection",
+                "vibe is compiled without the emotion feature",
+            ));
+        }
+        if options.emotion_model_path.is_none() {
+            return Err(TranscribeError::invalid_options(
+                "emotion_model_path",
+                "required for emotion detection",
+            ));
+        }
+    }
+
+    Ok(CheckedOptions {
+        suppress_tokens,
+        warnings,
+        profanity_list,
+        pii_patterns,
+        deadline,
+        partial_on_timeout,
+    })
+}
+
+/// Segment post processing of the options. returns the count of filtered profanity and masked PII
+fn post_process_segments(
+    segments: &mut Vec<Segment>,
+    options: &TranscribeOptions,
+    profanity_list: Option<&[String]>,
+    pii_patterns: Option<&[(String, Regex)]>,
+) -> (Option<usize>, Option<Vec<pii_filter::PiiEntityCount>>) {
+    if let Some(true) = options.sentence_segmentation {
+        *segments = segment_utils::split_sentences(std::mem::take(segments));
+    }
+    if let Some(true) = options.sentence_timestamps {
+        *segments = post_process::sentence_timestamps(std::mem::take(segments));
+    }
+    if let Some(max_segment_duration_secs) = options.max_segment_duration_secs {
+        *segments = segment_utils::split_long_segments(std::mem::take(segments), (max_segment_duration_secs * 100.0) as i64);
+    }
+    if let Some(min_segment_duration_secs) = options.min_segment_duration_secs {
+        *segments = segment_utils::merge_short_segments(std::mem::take(segments), (min_segment_duration_secs * 100.0) as i64);
+    }
+    if let Some(true) = options.auto_punctuate {
+        post_process::auto_punctuate(segments);
+    }
+    if options.speaker_change_markers == Some(true) && segments.iter().all(|segment| segment.speaker.is_none()) {
+        let markers = post_process::mark_speaker_changes(segments);
+        tracing::debug!("inserted {} speaker change markers", markers);
+    }
+    // Filter the final text so words joined by post processing are masked too
+    let replacement = options
+        .profanity_replacement
+        .as_deref()
+        .unwrap_or(DEFAULT_PROFANITY_REPLACEMENT);
+    let words_filtered = profanity_list.map(|words| post_process::filter_profanity(segments, words, replacement));
+    let pii_entities_found = pii_patterns.map(|patterns| pii_filter::mask_pii(segments, patterns));
+    (words_filtered, pii_entities_found)
+}
+
+/// Aggregate confidence, `max_segment_count` truncation and partial status of timed out transcription
+fn finish_transcript(transcript: &mut Transcript, options: &TranscribeOptions, timed_out: bool) {
+    transcript.compute_confidence();
+    if let Some(max_segment_count) = options.max_segment_count {
+        transcript.truncate(max_segment_count);
+    }
+    if timed_out {
+        tracing::warn!("timed out. returning {} segments", transcript.segments.len());
+        transcript.status = Some("partial".into());
+        transcript.truncated = Some(true);
+        transcript.truncated_at_secs = transcript.segments.last().map(|s| s.stop as f32 / 100.0);
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        path = %options.path,
+        file_size_bytes,
+        audio_duration_secs,
+        transcription_duration_ms
+    )
+)]
 pub fn transcribe(
     ctx: &WhisperContext,
     options: &TranscribeOptions,
@@ -136,27 +794,153 @@ pub fn transcribe(
     new_segment_callback: Option<Box<dyn Fn(Segment)>>,
     abort_callback: Option<Box<dyn Fn() -> bool>>,
     diarize_options: Option<DiarizeOptions>,
-) -> Result<Transcript> {
+) -> Result<Transcript, TranscribeError> {
     tracing::debug!("Transcribe called with {:?}", options);
 
+    let options = resolve_prompt_file(options)?;
+    let options = options.as_ref();
+    if options.tokenize_only == Some(true) {
+        let tokens = tokenize(ctx, initial_prompt(options).as_deref().unwrap_or_default())?;
+        return Ok(Transcript {
+            token_count: Some(tokens.len()),
+            tokens: Some(tokens),
+            ..Default::default()
+        });
+    }
     if !PathBuf::from(options.path.clone()).exists() {
-        bail!("audio file doesn't exist")
+        return Err(TranscribeError::invalid_options("path", "audio file doesn't exist"));
+    }
+    let span = tracing::Span::current();
+    if let Ok(metadata) = std::fs::metadata(&options.path) {
+        span.record("file_size_bytes", metadata.len());
+    }
+
+    let CheckedOptions {
+        suppress_tokens,
+        warnings,
+        profanity_list,
+        pii_patterns,
+        deadline,
+        partial_on_timeout,
+    } = check_options(ctx, options, diarize_options.is_some())?;
+
+    let mut segment_filter = SegmentFilter::new(options);
+    let start_offset = (options.start_secs.unwrap_or_default() * 100.0) as i64;
+    if options.start_secs.is_some_and(|start| start < 0.0) {
+        return Err(TranscribeError::invalid_options("start_secs", "must not be negative"));
+    }
+    if let (Some(start_secs), Some(end_secs)) = (options.start_secs, options.end_secs) {
+        if end_secs <= start_secs {
+            return Err(TranscribeError::invalid_options(
+                "end_secs",
+                "must be greater than start_secs",
+            ));
+        }
+    }
+
+    let strategy = sampling_strategy(options)?;
+    let dtw = options.dtw_timestamps == Some(true);
+    let token_timestamps = options.token_level_timestamps == Some(true);
+
+    if options.multichannel == Some(true) || options.split_channels == Some(true) {
+        return transcribe_multichannel(
+            ctx,
+            options,
+            progress_callback,
+            new_segment_callback,
+            abort_callback,
+            deadline,
+        );
     }
 
-    let out_path = if should_normalize(options.path.clone().into()) {
-        create_normalized_audio(options.path.clone().into())?
+    // Timestamps relative to the original audio
+    let new_segment_callback = match new_segment_callback {
+        Some(callback) if start_offset > 0 => Some(Box::new(move |mut segment: Segment| {
+            segment.start += start_offset;
+            segment.stop += start_offset;
+            callback(segment)
+        }) as Box<dyn Fn(Segment)>),
+        callback => callback,
+    };
+
+    // Abort when the timeout passes
+    let timed_out = Rc::new(Cell::new(false));
+    let abort_callback = match deadline {
+        Some(deadline) => {
+            let timed_out = timed_out.clone();
+            Some(Box::new(move || {
+                if Instant::now() >= deadline {
+                    timed_out.set(true);
+                    return true;
+                }
+                abort_callback.as_ref().is_some_and(|callback| callback())
+            }) as Box<dyn Fn() -> bool>)
+        }
+        None => abort_callback,
+    };
+
+    let gain_db = audio_gain_db(options);
+    let convert_options = audio::ConvertOptions {
+        loudnorm_target: match (options.normalize_audio, gain_db) {
+            (Some(false), _) | (None, Some(_)) => None,
+            _ => Some(options.normalize_target_lufs.unwrap_or(DEFAULT_NORMALIZE_TARGET_LUFS)),
+        },
+        start_secs: options.start_secs,
+        end_secs: options.end_secs,
+        gain_db,
+    };
+    let is_time_range = options.start_secs.is_some() || options.end_secs.is_some();
+    let mut original_info = None;
+    // Kept until the end so the normalized audio is removed on every return
+    let (normalized_path, loudness) = if is_time_range
+        || gain_db.is_some()
+        || options.normalize_audio == Some(true)
+        || should_normalize(options.path.clone().into())
+    {
+        original_info = audio::probe_audio(Path::new(&options.path))
+            .map_err(|e| tracing::debug!("failed to probe audio: {:?}", e))
+            .ok();
+        let (path, loudness) =
+            create_normalized_audio(options.path.clone().into(), &convert_options).map_err(TranscribeError::AudioDecodeError)?;
+        (Some(path), loudness)
     } else {
         tracing::debug!("Skip normalize");
-        options.path.clone().into()
+        (None, None)
     };
+    let out_path = normalized_path
+        .as_ref()
+        .map(|path| path.to_path_buf())
+        .unwrap_or_else(|| options.path.clone().into());
     tracing::debug!("out path is {}", out_path.display());
-    let original_samples = audio::parse_wav_file(&out_path)?;
+    let original_samples = audio::parse_wav_file(&out_path).map_err(TranscribeError::AudioDecodeError)?;
+    span.record("audio_duration_secs", original_samples.len() as f64 / 16000.0);
+    if is_time_range {
+        let duration_secs = original_samples.len() as f32 / 16000.0;
+        let start_secs = options.start_secs.unwrap_or_default();
+        if duration_secs == 0.0 {
+            return Err(TranscribeError::invalid_options(
+                "start_secs",
+                format!("{} exceeds the audio duration", start_secs),
+            ));
+        }
+        // Allow small rounding difference from ffmpeg
+        if let Some(end_secs) = options.end_secs {
+            if end_secs - start_secs - duration_secs > 0.1 {
+                return Err(TranscribeError::invalid_options(
+                    "end_secs",
+                    format!("{} exceeds the audio duration", end_secs),
+                ));
+            }
+        }
+    }
 
     let mut state = ctx.create_state().context("failed to create key")?;
 
-    let mut params = setup_params(options);
+    let mut params = setup_params(options, strategy);
 
     let mut segments = Vec::new();
+    // Set when whisper stops because of the abort callback
+    let aborted = Rc::new(Cell::new(false));
 
     let st = std::time::Instant::now();
     if let Some(diarize_options) = diarize_options {
@@ -168,9 +952,17 @@ pub fn transcribe(
         let mut embedding_manager = pyannote_rs::EmbeddingManager::new(diarize_options.max_speakers);
         let mut extractor =
             pyannote_rs::EmbeddingExtractor::new(diarize_options.embedding_model_path).map_err(|e| eyre!("{:?}", e))?;
+        #[cfg(feature = "emotion")]
+        let emotion_classifier = match (options.emotion_detection, &options.emotion_model_path) {
+            (Some(true), Some(model_path)) => Some(crate::emotion::EmotionClassifier::new(Path::new(model_path))?),
+            _ => None,
+        };
         for (i, diarize_segment) in diarize_segments.iter().enumerate() {
             if let Some(ref abort_callback) = abort_callback {
                 if abort_callback() {
+                    if timed_out.get() && !(partial_on_timeout && !segments.is_empty()) {
+                        return Err(TranscribeError::Timeout);
+                    }
                     break;
                 }
             }
@@ -180,7 +972,8 @@ pub fn transcribe(
 
             let mut samples = vec![0.0f32; diarize_segment.samples.len()];
 
-            whisper_rs::convert_integer_to_float_audio(&diarize_segment.samples, &mut samples)?;
+            whisper_rs::convert_integer_to_float_audio(&diarize_segment.samples, &mut samples)
+                .context("failed to convert samples")?;
             state.full(params.clone(), &samples).context("failed to transcribe")?;
 
             let num_segments = state.full_n_segments().context("failed to get number of segments")?;
@@ -201,6 +994,12 @@ pub fn transcribe(
                         continue; // Skip to the next segment
                     }
                 };
+                #[cfg(feature = "emotion")]
+                let emotion = emotion_classifier
+                    .as_ref()
+                    .and_then(|classifier| classifier.classify(&embedding_result));
+                #[cfg(not(feature = "emotion"))]
+                let emotion: Option<(String, f32)> = None;
                 // Find the speaker
                 let speaker = if embedding_manager.get_all_speakers().len() == diarize_options.max_speakers {
                     embedding_manager
@@ -215,15 +1014,22 @@ pub fn transcribe(
                 };
 
                 // convert to whisper comptible timestamps
-                let start = 100 * (diarize_segment.start as i64);
-                let stop = 100 * (diarize_segment.end as i64);
-                let text = state.full_get_segment_text_lossy(0).context("failed to get segment")?;
-                let segment = Segment {
+                let mut segment = Segment {
                     speaker: Some(speaker),
-                    start,
-                    stop,
-                    text,
+                    emotion_confidence: emotion.as_ref().map(|(_, confidence)| *confidence),
+                    emotion: emotion.map(|(emotion, _)| emotion),
+                    start: 100 * (diarize_segment.start as i64),
+                    stop: 100 * (diarize_segment.end as i64),
+                    ..read_segment(ctx, &state, 0, &suppress_tokens, dtw, token_timestamps)?
                 };
+                // token timestamps are relative to the diarized segment
+                for token in segment.tokens.iter_mut().flatten() {
+                    token.start = token.start.map(|start| start + diarize_segment.start as f32);
+                    token.end = token.end.map(|end| end + diarize_segment.start as f32);
+                }
+                if !segment_filter.keep(&mut segment) {
+                    continue;
+                }
                 segments.push(segment.clone());
 
                 if let Some(ref new_segment_callback) = new_segment_callback {
@@ -245,22 +1051,27 @@ pub fn transcribe(
         }
         let mut samples = vec![0.0f32; original_samples.len()];
 
-        whisper_rs::convert_integer_to_float_audio(&original_samples, &mut samples)?;
+        whisper_rs::convert_integer_to_float_audio(&original_samples, &mut samples).context("failed to convert samples")?;
 
         if let Some(new_segment_callback) = new_segment_callback {
             let internal_new_segmet_callback = move |segment: SegmentCallbackData| {
                 new_segment_callback(Segment {
                     start: segment.start_timestamp,
                     stop: segment.end_timestamp,
-                    speaker: None,
                     text: segment.text,
+                    ..Default::default()
                 })
             };
             params.set_segment_callback_safe_lossy(internal_new_segmet_callback);
         }
 
         if let Some(abort_callback) = abort_callback {
-            params.set_abort_callback_safe(abort_callback);
+            let aborted = aborted.clone();
+            params.set_abort_callback_safe(move || {
+                let abort = abort_callback();
+                aborted.set(abort);
+                abort
+            });
         }
 
         if PROGRESS_CALLBACK.lock().map_err(|e| eyre!("{:?}", e))?.as_ref().is_some() {
@@ -283,40 +1094,158 @@ pub fn transcribe(
         tracing::debug!("set start time...");
 
         tracing::debug!("setting state full...");
-        state.full(params, &samples).context("failed to transcribe")?;
+        let result = state.full(params, &samples);
+        if timed_out.get() && !partial_on_timeout {
+            return Err(TranscribeError::Timeout);
+        }
+        if aborted.get() && !timed_out.get() {
+            return Err(TranscribeError::Aborted);
+        }
+        // The state keeps the segments decoded before the timeout
+        if !timed_out.get() {
+            result.context("failed to transcribe")?;
+        }
         let _et = std::time::Instant::now();
 
         tracing::debug!("getting segments count...");
         let num_segments = state.full_n_segments().context("failed to get number of segments")?;
         if num_segments == 0 {
-            bail!("no segements found!")
+            if timed_out.get() {
+                return Err(TranscribeError::Timeout);
+            }
+            return Err(eyre!("no segements found!").into());
         }
         tracing::debug!("found {} sentence segments", num_segments);
 
         tracing::debug!("looping segments...");
+        let tdrz = options.tdrz == Some(true);
+        let mut speaker = 0;
         for s in 0..num_segments {
-            let text = state.full_get_segment_text_lossy(s).context("failed to get segment")?;
-            let start = state.full_get_segment_t0(s).context("failed to get start timestamp")?;
-            let stop = state.full_get_segment_t1(s).context("failed to get end timestamp")?;
-            segments.push(Segment {
-                text,
-                start,
-                stop,
-                speaker: None,
-            });
+            let mut segment = read_segment(ctx, &state, s, &suppress_tokens, dtw, token_timestamps)?;
+            if tdrz {
+                segment.speaker = Some(speaker.to_string());
+                // Segments end at the speaker turn token, the next one is of the other speaker
+                if state.full_get_segment_speaker_turn_next(s) {
+                    speaker = 1 - speaker;
+                }
+            }
+            if segment_filter.keep(&mut segment) {
+                segments.push(segment);
+            }
         }
     }
 
-    #[allow(unused_mut)]
+    for segment in segments.iter_mut() {
+        segment.start += start_offset;
+        segment.stop += start_offset;
+    }
+
+    let (words_filtered, pii_entities_found) =
+        post_process_segments(&mut segments, options, profanity_list.as_deref(), pii_patterns.as_deref());
+
+    span.record("transcription_duration_ms", st.elapsed().as_millis() as u64);
+
     let mut transcript = Transcript {
         segments,
         processing_time_sec: Instant::now().duration_since(st).as_secs(),
+        audio_meta: original_info.map(|info| AudioMeta {
+            original_sample_rate: info.sample_rate,
+            original_channels: info.channels,
+            original_duration_secs: info.duration_secs as f32,
+            processed_sample_rate: 16000,
+            processed_channels: 1,
+            processed_duration_secs: original_samples.len() as f32 / 16000.0,
+            normalization_applied: loudness.is_some(),
+            input_loudness_lufs: loudness.map(|loudness| loudness.input),
+            output_loudness_lufs: loudness.map(|loudness| loudness.output),
+        }),
+        preprocessing: gain_db.map(|gain_db| Preprocessing {
+            gain_applied_db: Some(gain_db),
+        }),
+        hallucination_count: segment_filter
+            .hallucination_filter
+            .then_some(segment_filter.hallucination_count),
+        words_filtered,
+        pii_entities_found,
+        warnings: (!warnings.is_empty()).then_some(warnings),
+        ..Default::default()
     };
+    finish_transcript(&mut transcript, options, timed_out.get());
+
+    Ok(transcript)
+}
 
-    // cleanup
-    if out_path.starts_with(std::env::temp_dir()) {
-        std::fs::remove_file(out_path)?;
+/// Transcribe 16KHz mono PCM samples in range -1..1 which are already in memory.
+/// Unlike `transcribe` there's no file I/O, audio conversion or diarization. `options.path` is ignored
+#[tracing::instrument(skip_all, fields(audio_duration_secs = samples.len() as f64 / 16000.0))]
+pub fn transcribe_raw_audio(
+    ctx: &WhisperContext,
+    options: &TranscribeOptions,
+    samples: &[f32],
+) -> Result<Transcript, TranscribeError> {
+    if samples.is_empty() {
+        return Err(TranscribeError::invalid_options("samples", "must not be empty"));
     }
+    let options = resolve_prompt_file(options)?;
+    let options = options.as_ref();
+    let CheckedOptions {
+        suppress_tokens,
+        warnings,
+        profanity_list,
+        pii_patterns,
+        deadline,
+        partial_on_timeout,
+    } = check_options(ctx, options, false)?;
 
+    let st = Instant::now();
+    let dtw = options.dtw_timestamps == Some(true);
+    let token_timestamps = options.token_level_timestamps == Some(true);
+    let mut params = setup_params(options, sampling_strategy(options)?);
+    let timed_out = Rc::new(Cell::new(false));
+    if let Some(deadline) = deadline {
+        let timed_out = timed_out.clone();
+        params.set_abort_callback_safe(move || {
+            let abort = Instant::now() >= deadline;
+            timed_out.set(abort);
+            abort
+        });
+    }
+    let mut state = ctx.create_state().context("failed to create key")?;
+    let result = state.full(params, samples);
+    if timed_out.get() && !partial_on_timeout {
+        return Err(TranscribeError::Timeout);
+    }
+    // The state keeps the segments decoded before the timeout
+    if !timed_out.get() {
+        result.context("failed to transcribe")?;
+    }
+
+    let mut segment_filter = SegmentFilter::new(options);
+    let num_segments = state.full_n_segments().context("failed to get number of segments")?;
+    if num_segments == 0 && timed_out.get() {
+        return Err(TranscribeError::Timeout);
+    }
+    let mut segments = Vec::new();
+    for s in 0..num_segments {
+        let mut segment = read_segment(ctx, &state, s, &suppress_tokens, dtw, token_timestamps)?;
+        if segment_filter.keep(&mut segment) {
+            segments.push(segment);
+        }
+    }
+    let (words_filtered, pii_entities_found) =
+        post_process_segments(&mut segments, options, profanity_list.as_deref(), pii_patterns.as_deref());
+
+    let mut transcript = Transcript {
+        segments,
+        processing_time_sec: st.elapsed().as_secs(),
+        hallucination_count: segment_filter
+            .hallucination_filter
+            .then_some(segment_filter.hallucination_count),
+        words_filtered,
+        pii_entities_found,
+        warnings: (!warnings.is_empty()).then_some(warnings),
+        ..Default::default()
+    };
+    finish_transcript(&mut transcript, options, timed_out.get());
     Ok(transcript)
 }
@@ -1,3 +1,5 @@
+use crate::config::ConfigWarning;
+use crate::pii_filter::PiiEntityCount;
 use eyre::Result;
 use num::integer::div_floor;
 use serde::{Deserialize, Serialize};
@@ -25,19 +27,218 @@ pub fn format_timestamp(seconds: i64, always_include_hours: bool, decimal_marker
     format!("{hours_marker}{minutes:02}:{seconds:02}{decimal_marker}{milliseconds:03}")
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+/// ASS timestamp (H:MM:SS.cc). timestamps are in centiseconds, negative ones are clamped to 0
+pub fn format_ass_timestamp(centiseconds: i64) -> String {
+    let centiseconds = centiseconds.max(0);
+    let hours = centiseconds / 360_000;
+    let minutes = (centiseconds % 360_000) / 6_000;
+    let seconds = (centiseconds % 6_000) / 100;
+    let centiseconds = centiseconds % 100;
+    format!("{hours}:{minutes:02}:{seconds:02}.{centiseconds:02}")
+}
+
+/// Advanced SubStation Alpha v4.00+ header (the `[V4+ Styles]` dialect used by libass, mpv and VLC)
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 384
+PlayResY: 288
+WrapStyle: 0
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,16,&H00FFFFFF,&H0000FFFF,&H00000000,&H80000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// Max words in a single karaoke line
+const ASS_MAX_LINE_WORDS: usize = 10;
+/// Bottom of screen region for WebVTT cues
+const VTT_REGION: &str = "\nREGION\nid:r0\nwidth:100%\nlines:3\nregionanchor:0%,100%\nviewportanchor:0%,90%\n";
+
+/// Appearance of WebVTT cues. colors and font size are written to a `STYLE` block,
+/// position and align are cue settings
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
+pub struct VttStyle {
+    /// CSS color, eg. `yellow` or `#ffff00`
+    pub color: Option<String>,
+    pub background: Option<String>,
+    /// CSS font size, eg. `120%`
+    pub font_size: Option<String>,
+    /// Horizontal position in percent of the video width
+    pub position: Option<f32>,
+    /// start, center, end, left or right
+    pub align: Option<String>,
+}
+
+/// Keep the value inside the CSS declaration or cue setting
+fn vtt_style_value(value: &str) -> String {
+    value.replace(['{', '}', ';', '\n', ' '], "").replace("-->", "")
+}
+
+impl VttStyle {
+    fn cue_settings(&self) -> String {
+        let mut settings = String::new();
+        if let Some(position) = self.position {
+            settings += &format!(" position:{}%", position.clamp(0.0, 100.0));
+        }
+        if let Some(align) = self.align.as_deref() {
+            settings += &format!(" align:{}", vtt_style_value(align));
+        }
+        settings
+    }
+
+    fn style_block(&self) -> Option<String> {
+        let declarations: Vec<String> = [
+            ("color", &self.color),
+            ("background-color", &self.background),
+            ("font-size", &self.font_size),
+        ]
+        .iter()
+        .filter_map(|(property, value)| {
+            value
+                .as_deref()
+                .map(|value| format!("  {}: {};\n", property, vtt_style_value(value)))
+        })
+        .collect();
+        if declarations.is_empty() {
+            return None;
+        }
+        Some(format!("\nSTYLE\n::cue {{\n{}}}\n", declarations.concat()))
+    }
+}
+
+/// Start new karaoke line when words are further apart (centiseconds)
+const ASS_MAX_WORDS_GAP: i64 = 100;
+
+/// Audio before and after the conversion to whisper format
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
+pub struct AudioMeta {
+    pub original_sample_rate: u32,
+    pub original_channels: u16,
+    pub original_duration_secs: f32,
+    pub processed_sample_rate: u32,
+    pub processed_channels: u16,
+    /// Duration of the samples transcribed by whisper. shorter than the original with `start_secs` / `end_secs`
+    pub processed_duration_secs: f32,
+    pub normalization_applied: bool,
+    /// Integrated loudness before normalization in LUFS
+    pub input_loudness_lufs: Option<f32>,
+    /// Integrated loudness after normalization in LUFS
+    pub output_loudness_lufs: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
+pub struct Preprocessing {
+    /// Volume gain applied before transcribe in dB
+    pub gain_applied_db: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
 pub struct Transcript {
     pub processing_time_sec: u64,
     pub segments: Vec<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_meta: Option<AudioMeta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<Preprocessing>,
+    /// Number of segments detected as possible hallucinations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hallucination_count: Option<usize>,
+    /// Peak memory increase while transcribing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_used_bytes: Option<u64>,
+    /// Requests ahead in the server queue when this one was queued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Queue wait estimated from the average request duration of the server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_wait_secs: Option<f32>,
+    /// `partial` when the transcription timed out and `timeout_action` is `partial`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Segments were dropped after `max_segment_count` or the transcription timed out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<bool>,
+    /// End of the last returned segment in seconds when truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated_at_secs: Option<f32>,
+    /// Number of segments before truncation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_segment_count: Option<usize>,
+    /// Number of words masked by the profanity filter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub words_filtered: Option<usize>,
+    /// Count of every PII type masked by `mask_pii`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pii_entities_found: Option<Vec<PiiEntityCount>>,
+    /// Mean segment confidence weighted by segment duration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_confidence: Option<f32>,
+    /// p25, p50, p75 and p99 of the segments confidence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence_quartiles: Option<Vec<f32>>,
+    /// Segments with confidence below 0.4
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_confidence_segment_count: Option<usize>,
+    /// Transcript of every channel when transcribed with `split_channels`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<ChannelTranscript>>,
+    /// Tokens of the initial prompt with `tokenize_only`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<Token>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_count: Option<usize>,
+    /// Options which have no effect or may produce wrong results together
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<ConfigWarning>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub id: i32,
+    /// Start in seconds. set with `token_level_timestamps`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<f32>,
+    /// Probability of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct ChannelTranscript {
+    pub channel: usize,
+    pub result: Transcript,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Default)]
 pub struct Segment {
     pub start: i64,
     pub stop: i64,
     pub text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub speaker: Option<String>,
+    /// Probability that the segment has no speech, before filtering by threshold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_speech_prob: Option<f32>,
+    /// Mean probability of the segment tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// Estimated risk (0-1) that the segment is hallucinated. set when hallucination filter is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hallucination_risk: Option<f32>,
+    /// Approximate emotion of the speaker: neutral, happy, sad, angry, fearful or surprised
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emotion: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emotion_confidence: Option<f32>,
+    /// Text tokens with their timestamps. set with `token_level_timestamps`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<Token>>,
 }
 
 impl Segment {
@@ -45,12 +246,23 @@ impl Segment {
         self.text.to_owned()
     }
 
-    pub fn as_vtt(&self) -> String {
+    /// WebVTT cue with identifier. speaker is added as voice span
+    pub fn as_vtt(&self, index: i32, region: Option<&str>, style: Option<&VttStyle>) -> String {
+        let text = vtt_escape(self.text.trim());
+        let payload = match self.speaker.as_deref() {
+            Some(speaker) => format!("<v {}>{}</v>", vtt_escape(speaker), text),
+            None => text,
+        };
+        let mut settings = region.map(|region| format!(" region:{}", region)).unwrap_or_default();
+        if let Some(style) = style {
+            settings += &style.cue_settings();
+        }
         format!(
-            "{} --> {}\n{}\n",
+            "\n{index}\n{} --> {}{}\n{}\n",
             format_timestamp(self.start, false, "."),
             format_timestamp(self.stop, false, "."),
-            self.text.trim().replace("-->", "->")
+            settings,
+            payload
         )
     }
 
@@ -62,9 +274,60 @@ impl Segment {
             self.text.trim().replace("-->", "->")
         )
     }
+
+    /// Audacity label: start and end in seconds and the label separated by tabs. speaker is prefixed to the label
+    pub fn as_audacity_label(&self) -> String {
+        let text = self.text.trim().replace(['\t', '\n'], " ");
+        let label = match self.speaker.as_deref() {
+            Some(speaker) => format!("{}: {}", speaker, text),
+            None => text,
+        };
+        format!(
+            "{:.6}\t{:.6}\t{}\n",
+            self.start as f64 / 100.0,
+            self.stop as f64 / 100.0,
+            label
+        )
+    }
 }
 
+/// Segments below this confidence are counted as low confidence
+const LOW_CONFIDENCE: f32 = 0.4;
+
 impl Transcript {
+    /// Set the aggregate confidence fields from the segments confidence
+    pub fn compute_confidence(&mut self) {
+        let segments: Vec<(f32, f32)> = self
+            .segments
+            .iter()
+            .filter_map(|s| s.confidence.map(|confidence| (confidence, (s.stop - s.start).max(1) as f32)))
+            .collect();
+        if segments.is_empty() {
+            return;
+        }
+        let total_duration: f32 = segments.iter().map(|(_, duration)| duration).sum();
+        let weighted: f32 = segments.iter().map(|(confidence, duration)| confidence * duration).sum();
+        self.transcript_confidence = Some(weighted / total_duration);
+
+        let mut sorted: Vec<f32> = segments.iter().map(|(confidence, _)| *confidence).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        // Nearest rank percentile
+        let percentile = |p: f32| sorted[((p * sorted.len() as f32).ceil() as usize).clamp(1, sorted.len()) - 1];
+        self.confidence_quartiles = Some(vec![percentile(0.25), percentile(0.5), percentile(0.75), percentile(0.99)]);
+        self.low_confidence_segment_count = Some(sorted.iter().filter(|confidence| **confidence < LOW_CONFIDENCE).count());
+    }
+
+    /// Keep only the first `max_segments` segments
+    pub fn truncate(&mut self, max_segments: usize) {
+        self.total_segment_count = Some(self.segments.len());
+        if self.segments.len() <= max_segments {
+            return;
+        }
+        self.segments.truncate(max_segments);
+        self.truncated = Some(true);
+        self.truncated_at_secs = Some(self.segments.last().map(|s| s.stop as f32 / 100.0).unwrap_or_default());
+    }
+
     pub fn as_text(&self) -> String {
         self.segments
             .iter()
@@ -75,10 +338,23 @@ impl Transcript {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
-    pub fn as_vtt(&self) -> String {
+    /// WebVTT subtitles. with `regions` cues are placed in a 3 lines region at the bottom of the screen
+    /// and `style` adds a `STYLE` block and position / align cue settings
+    pub fn as_vtt(&self, regions: bool, style: Option<&VttStyle>) -> String {
+        let mut vtt = "WEBVTT\n".to_string();
+        if let Some(style_block) = style.and_then(VttStyle::style_block) {
+            vtt += &style_block;
+        }
+        if regions {
+            vtt += VTT_REGION;
+        }
+        let region = regions.then_some("r0");
         self.segments
             .iter()
-            .fold(String::new(), |transcript, fragment| transcript + fragment.as_vtt().as_str())
+            .fold((1, vtt), |(i, transcript), fragment| {
+                (i + 1, transcript + fragment.as_vtt(i, region, style).as_str())
+            })
+            .1
     }
 
     pub fn as_srt(&self) -> String {
@@ -89,4 +365,76 @@ impl Transcript {
             })
             .1
     }
+
+    /// Audacity label track
+    pub fn as_audacity_labels(&self) -> String {
+        self.segments.iter().fold(String::new(), |transcript, fragment| {
+            transcript + fragment.as_audacity_label().as_str()
+        })
+    }
+
+    /// ASS subtitles. with `karaoke` every segment is treated as a single word (word timestamps)
+    /// and grouped into lines where each word is timed with `{\k}` tags
+    pub fn as_ass(&self, karaoke: bool) -> String {
+        let mut ass = ASS_HEADER.to_string();
+        let dialogue = |start: i64, stop: i64, text: &str| {
+            format!(
+                "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+                format_ass_timestamp(start),
+                format_ass_timestamp(stop),
+                text
+            )
+        };
+        if !karaoke {
+            for segment in &self.segments {
+                ass += &dialogue(segment.start, segment.stop, &ass_escape(segment.text.trim()));
+            }
+            return ass;
+        }
+
+        let mut lines: Vec<Vec<&Segment>> = Vec::new();
+        for segment in &self.segments {
+            let start_new_line = match lines.last().and_then(|line| line.last()) {
+                Some(prev) => {
+                    let line_len = lines.last().map(|line| line.len()).unwrap_or_default();
+                    let prev_text = prev.text.trim_end();
+                    line_len >= ASS_MAX_LINE_WORDS
+                        || segment.start - prev.stop > ASS_MAX_WORDS_GAP
+                        || prev_text.ends_with(['.', '?', '!'])
+                }
+                None => true,
+            };
+            if start_new_line {
+                lines.push(vec![segment]);
+            } else if let Some(line) = lines.last_mut() {
+                line.push(segment);
+            }
+        }
+
+        for line in lines {
+            let (Some(first), Some(last)) = (line.first(), line.last()) else {
+                continue;
+            };
+            let mut text = String::new();
+            let mut prev_stop = first.start;
+            for word in &line {
+                let gap = word.start - prev_stop;
+                if gap > 0 {
+                    text += &format!("{{\\k{}}}", gap);
+                }
+                text += &format!("{{\\k{}}}{} ", (word.stop - word.start).max(0), ass_escape(word.text.trim()));
+                prev_stop = word.stop.max(word.start);
+            }
+            ass += &dialogue(first.start, last.stop, text.trim_end());
+        }
+        ass
+    }
+}
+
+fn vtt_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn ass_escape(text: &str) -> String {
+    text.replace('{', "(").replace('}', ")").replace('\n', " ")
 }
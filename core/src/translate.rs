@@ -0,0 +1,47 @@
+use crate::transcript::Transcript;
+use eyre::{bail, Context, OptionExt, Result};
+use serde_json::{json, Value};
+
+/// Environment variable with the base URL of a LibreTranslate compatible server
+pub const TRANSLATE_URL_ENV: &str = "VIBE_TRANSLATE_URL";
+
+/// Translate text using LibreTranslate REST API.
+/// Whisper's built-in translation only supports English as target, this is used for the rest.
+pub async fn translate_text(client: &reqwest::Client, text: &str, source: &str, target: &str) -> Result<String> {
+    let base_url = std::env::var(TRANSLATE_URL_ENV).context(format!("{} is not set", TRANSLATE_URL_ENV))?;
+    let url = format!("{}/translate", base_url.trim_end_matches('/'));
+    let body = json!({
+        "q": text,
+        "source": source,
+        "target": target,
+        "format": "text",
+    });
+    let res = client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .context(format!("failed to send translate request to {}", url))?;
+    let status = res.status();
+    let res_text = res.text().await?;
+    if !status.is_success() {
+        bail!("translate request failed with status {}: {}", status, res_text);
+    }
+    let value: Value = serde_json::from_str(&res_text).context("failed to parse translate response")?;
+    let translated = value["translatedText"]
+        .as_str()
+        .ok_or_eyre("translatedText missing in response")?;
+    Ok(translated.to_string())
+}
+
+/// Translate every segment of the transcript, keeping the timestamps
+pub async fn translate_transcript(transcript: &mut Transcript, source: &str, target: &str) -> Result<()> {
+    let client = reqwest::Client::new();
+    for segment in transcript.segments.iter_mut() {
+        tracing::debug!("translate segment {} -> {}: {}", source, target, segment.text);
+        let translated = translate_text(&client, segment.text.trim(), source, target).await?;
+        segment.text = format!(" {}", translated);
+    }
+    Ok(())
+}
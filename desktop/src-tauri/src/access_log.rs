@@ -0,0 +1,71 @@
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, Response};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tower_http::trace::{MakeSpan, OnResponse};
+use tracing::{Level, Span};
+
+/// One access log event per request at the configured level.
+/// method, path and client ip are recorded in the request span and status, latency and size in the event
+#[derive(Clone, Copy)]
+pub struct AccessLog {
+    level: Level,
+}
+
+impl AccessLog {
+    pub fn new(level: Level) -> Self {
+        Self { level }
+    }
+}
+
+/// First address of X-Forwarded-For or the socket address
+fn client_ip<B>(request: &Request<B>) -> String {
+    request
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|ip| ip.trim().to_string())
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        })
+        .unwrap_or_default()
+}
+
+impl<B> MakeSpan<B> for AccessLog {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let method = request.method().as_str();
+        let path = request.uri().path();
+        let client_ip = client_ip(request);
+        match self.level {
+            Level::ERROR => tracing::error_span!("access", method, path, client_ip),
+            Level::WARN => tracing::warn_span!("access", method, path, client_ip),
+            Level::INFO => tracing::info_span!("access", method, path, client_ip),
+            Level::DEBUG => tracing::debug_span!("access", method, path, client_ip),
+            Level::TRACE => tracing::trace_span!("access", method, path, client_ip),
+        }
+    }
+}
+
+impl<B> OnResponse<B> for AccessLog {
+    fn on_response(self, response: &Response<B>, latency: Duration, _span: &Span) {
+        let status = response.status().as_u16();
+        let latency_ms = latency.as_millis() as u64;
+        // Streamed responses have no content length
+        let bytes_sent = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        match self.level {
+            Level::ERROR => tracing::error!(status, latency_ms, bytes_sent, "request completed"),
+            Level::WARN => tracing::warn!(status, latency_ms, bytes_sent, "request completed"),
+            Level::INFO => tracing::info!(status, latency_ms, bytes_sent, "request completed"),
+            Level::DEBUG => tracing::debug!(status, latency_ms, bytes_sent, "request completed"),
+            Level::TRACE => tracing::trace!(status, latency_ms, bytes_sent, "request completed"),
+        }
+    }
+}
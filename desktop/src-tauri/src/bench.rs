@@ -0,0 +1,81 @@
+use crate::memory_tracker::MemoryTracker;
+use eyre::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::time::Instant;
+use sysinfo::{ProcessesToUpdate, System};
+use vibe_core::config::TranscribeOptions;
+use vibe_core::transcribe::{self, ContextOptions};
+
+#[derive(Serialize)]
+pub struct BenchmarkResult {
+    pub model: String,
+    pub iterations: usize,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p99_ms: u64,
+    /// Processing time relative to the audio duration. below 1 is faster than realtime
+    pub realtime_factor: f64,
+}
+
+/// Process CPU usage in percent of one core since the previous refresh
+fn refresh_cpu_usage(system: &mut System, pid: sysinfo::Pid) -> Option<f32> {
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+    system.process(pid).map(|process| process.cpu_usage())
+}
+
+/// Load the model once and transcribe the audio file `iterations` times.
+/// CPU usage and peak memory of every run are printed to stderr
+pub fn benchmark(model_path: &Path, options: &TranscribeOptions, iterations: usize) -> Result<BenchmarkResult> {
+    let iterations = iterations.max(1);
+    let audio_duration_secs = vibe_core::audio::get_audio_duration(Path::new(&options.path))?;
+    eprintln!("Load model {}...", model_path.display());
+    let ctx = transcribe::create_context(model_path, ContextOptions::default().with_overrides(options))?;
+
+    let pid = sysinfo::get_current_pid().ok();
+    let mut system = System::new();
+    let mut durations_ms = Vec::with_capacity(iterations);
+    for i in 1..=iterations {
+        if let Some(pid) = pid {
+            refresh_cpu_usage(&mut system, pid);
+        }
+        let memory_tracker = MemoryTracker::start();
+        let start = Instant::now();
+        transcribe::transcribe(&ctx, options, None, None, None, None).context(format!("run {} failed", i))?;
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        let peak_memory = memory_tracker.stop();
+        let cpu_usage = pid.and_then(|pid| refresh_cpu_usage(&mut system, pid));
+        eprintln!(
+            "run {}/{}: {}ms, cpu {}, peak memory {}",
+            i,
+            iterations,
+            elapsed_ms,
+            cpu_usage.map(|usage| format!("{:.0}%", usage)).unwrap_or("unknown".into()),
+            peak_memory
+                .map(|bytes| format!("{} MB", bytes / 1024 / 1024))
+                .unwrap_or("unknown".into())
+        );
+        durations_ms.push(elapsed_ms);
+    }
+
+    let mean_ms = durations_ms.iter().sum::<u64>() / durations_ms.len() as u64;
+    durations_ms.sort();
+    // Nearest rank percentile
+    let percentile = |p: f64| durations_ms[((p * durations_ms.len() as f64).ceil() as usize).clamp(1, durations_ms.len()) - 1];
+    let realtime_factor = if audio_duration_secs > 0.0 {
+        mean_ms as f64 / 1000.0 / audio_duration_secs
+    } else {
+        0.0
+    };
+    Ok(BenchmarkResult {
+        model: model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().trim_start_matches("ggml-").to_string())
+            .unwrap_or_default(),
+        iterations,
+        mean_ms,
+        p50_ms: percentile(0.5),
+        p99_ms: percentile(0.99),
+        realtime_factor: (realtime_factor * 100.0).round() / 100.0,
+    })
+}
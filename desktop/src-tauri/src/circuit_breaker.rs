@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failures which open the circuit
+const FAILURE_THRESHOLD: u32 = 3;
+/// Failures further apart than this are not counted as consecutive
+const FAILURE_WINDOW: Duration = Duration::from_secs(60);
+pub const RETRY_AFTER: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Circuit {
+    Closed,
+    /// Requests are rejected until retry after passed
+    Open {
+        opened_at: Instant,
+    },
+    /// One trial request is in flight
+    HalfOpen,
+}
+
+/// Circuit breaker for downloads from one host.
+/// When the remote host is down every request fails fast instead of waiting for the timeout
+#[derive(Debug)]
+pub struct CircuitBreakerState {
+    circuit: Circuit,
+    failures: u32,
+    last_failure_at: Option<Instant>,
+}
+
+impl Default for CircuitBreakerState {
+    fn default() -> Self {
+        Self {
+            circuit: Circuit::Closed,
+            failures: 0,
+            last_failure_at: None,
+        }
+    }
+}
+
+impl CircuitBreakerState {
+    /// Check if a request may start. returns the remaining time until retry when the circuit is open.
+    /// Once retry after passed a single trial request is allowed
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        match self.circuit {
+            Circuit::Closed => Ok(()),
+            Circuit::Open { opened_at } => {
+                let elapsed = opened_at.elapsed();
+                if elapsed < RETRY_AFTER {
+                    return Err(RETRY_AFTER - elapsed);
+                }
+                tracing::info!("download circuit half open. trying one request");
+                self.circuit = Circuit::HalfOpen;
+                Ok(())
+            }
+            Circuit::HalfOpen => Err(RETRY_AFTER),
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        if self.circuit != Circuit::Closed {
+            tracing::info!("download circuit closed");
+        }
+        self.circuit = Circuit::Closed;
+        self.failures = 0;
+        self.last_failure_at = None;
+    }
+
+    pub fn record_failure(&mut self) {
+        let now = Instant::now();
+        if self
+            .last_failure_at
+            .is_some_and(|last_failure_at| now.duration_since(last_failure_at) > FAILURE_WINDOW)
+        {
+            self.failures = 0;
+        }
+        self.failures += 1;
+        self.last_failure_at = Some(now);
+        if self.circuit == Circuit::HalfOpen || self.failures >= FAILURE_THRESHOLD {
+            tracing::warn!(
+                "download circuit open after {} consecutive failures. retry after {}s",
+                self.failures,
+                RETRY_AFTER.as_secs()
+            );
+            self.circuit = Circuit::Open { opened_at: now };
+        }
+    }
+}
+
+/// Circuit breakers by download host. failing URLs of one host don't block downloads from other hosts
+#[derive(Debug, Default)]
+pub struct DownloadCircuitBreakers {
+    hosts: HashMap<String, CircuitBreakerState>,
+}
+
+impl DownloadCircuitBreakers {
+    pub fn try_acquire(&mut self, host: &str) -> Result<(), Duration> {
+        self.hosts.entry(host.to_string()).or_default().try_acquire()
+    }
+
+    /// Closed circuits without failures are removed so the hosts don't pile up
+    pub fn record_success(&mut self, host: &str) {
+        if let Some(mut state) = self.hosts.remove(host) {
+            state.record_success();
+        }
+    }
+
+    pub fn record_failure(&mut self, host: &str) {
+        self.hosts.entry(host.to_string()).or_default().record_failure();
+    }
+}
+
+/// Acquired download of a host. dropping it before `finish` (eg. the client disconnected while downloading)
+/// records a failure, so a half open circuit doesn't stay half open forever
+pub struct DownloadPermit<'a> {
+    breakers: &'a Mutex<DownloadCircuitBreakers>,
+    host: String,
+    finished: bool,
+}
+
+impl<'a> DownloadPermit<'a> {
+    pub fn acquire(breakers: &'a Mutex<DownloadCircuitBreakers>, host: &str) -> Result<Self, Duration> {
+        if let Ok(mut breakers) = breakers.lock() {
+            breakers.try_acquire(host)?;
+        }
+        Ok(Self {
+            breakers,
+            host: host.to_string(),
+            finished: false,
+        })
+    }
+
+    pub fn finish(mut self, success: bool) {
+        self.finished = true;
+        self.record(success);
+    }
+
+    fn record(&self, success: bool) {
+        if let Ok(mut breakers) = self.breakers.lock() {
+            match success {
+                true => breakers.record_success(&self.host),
+                false => breakers.record_failure(&self.host),
+            }
+        }
+    }
+}
+
+impl Drop for DownloadPermit<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            tracing::debug!("download from {} dropped before finishing", self.host);
+            self.record(false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open circuit whose retry after already passed
+    fn expire(state: &mut CircuitBreakerState) {
+        let opened_at = Instant::now().checked_sub(RETRY_AFTER).unwrap();
+        state.circuit = Circuit::Open { opened_at };
+    }
+
+    #[test]
+    fn test_circuit_breaker_states() {
+        let mut state = CircuitBreakerState::default();
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            assert!(state.try_acquire().is_ok());
+            state.record_failure();
+        }
+        assert_eq!(state.circuit, Circuit::Closed);
+        state.record_failure();
+        assert!(matches!(state.circuit, Circuit::Open { .. }));
+        assert!(state.try_acquire().is_err());
+
+        expire(&mut state);
+        assert!(state.try_acquire().is_ok());
+        assert_eq!(state.circuit, Circuit::HalfOpen);
+        // Single trial request
+        assert!(state.try_acquire().is_err());
+        state.record_failure();
+        assert!(matches!(state.circuit, Circuit::Open { .. }));
+
+        expire(&mut state);
+        assert!(state.try_acquire().is_ok());
+        state.record_success();
+        assert_eq!(state.circuit, Circuit::Closed);
+        assert!(state.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn test_download_permit() {
+        let breakers = Mutex::new(DownloadCircuitBreakers::default());
+        for _ in 0..FAILURE_THRESHOLD {
+            DownloadPermit::acquire(&breakers, "bad.example").unwrap().finish(false);
+        }
+        assert!(DownloadPermit::acquire(&breakers, "bad.example").is_err());
+        // Other hosts aren't affected
+        DownloadPermit::acquire(&breakers, "good.example").unwrap().finish(true);
+
+        // Dropped half open download opens the circuit again
+        expire(breakers.lock().unwrap().hosts.get_mut("bad.example").unwrap());
+        drop(DownloadPermit::acquire(&breakers, "bad.example").unwrap());
+        let circuit = breakers.lock().unwrap().hosts["bad.example"].circuit;
+        assert!(matches!(circuit, Circuit::Open { .. }));
+    }
+}
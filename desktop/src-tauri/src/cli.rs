@@ -1,15 +1,24 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
 use eyre::{Context, ContextCompat, Result};
-use serde_json::Value;
+use globset::Glob;
+use notify::{EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::{json, Value};
 use std::path::{Path, PathBuf};
 use std::process;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tauri::AppHandle;
+use tempfile::TempPath;
+use utoipa::ToSchema;
 use vibe_core::config::TranscribeOptions;
-use vibe_core::transcribe;
+use vibe_core::post_process;
+use vibe_core::transcribe::{self, ContextOptions, WhisperContext};
+use vibe_core::transcript::Transcript;
 
+use crate::bench;
 use crate::cmd::get_models_folder;
 use crate::server;
+use crate::startup_checks;
 
 /// Attach to console if cli detected in Windows
 #[cfg(all(windows, not(debug_assertions)))]
@@ -55,10 +64,14 @@ struct Args {
     #[arg(long, short)]
     model: Option<PathBuf>,
 
-    /// Path to file to transcribe
+    /// Path to file to transcribe. use - to read from stdin
     #[arg(long)]
     file: Option<String>,
 
+    /// Format of the audio read from stdin (used for the temp file extension)
+    #[arg(long, default_value = "wav", value_parser = ["wav", "mp3", "ogg"])]
+    stdin_format: String,
+
     /// Language to transcribe
     #[arg(short, long, default_value = "english", value_parser = get_possible_languages())]
     language: String,
@@ -75,7 +88,12 @@ struct Args {
     #[arg(long)]
     translate: Option<bool>,
 
-    /// Max tokens
+    /// Language code to translate to (eg. fr).
+    /// Whisper translates only to English, other targets require the `translate` feature and VIBE_TRANSLATE_URL
+    #[arg(long)]
+    translate_to: Option<String>,
+
+    /// Max tokens of the previous text used as prompt. -1 means no limit
     #[arg(long)]
     max_text_ctx: Option<i32>,
 
@@ -83,18 +101,42 @@ struct Args {
     #[arg(short, long)]
     init_prompt: Option<String>,
 
+    /// JSON file with transcribe options in the same format as the server API. flags passed explicitly take precedence
+    #[arg(long)]
+    options_file: Option<PathBuf>,
+
+    /// Print the JSON Schema of the options file and exit. reference it with `"$schema"` in the file for editor completion
+    #[arg(long)]
+    config_schema: bool,
+
+    /// Insert a paragraph break when pause between segments is longer than this (txt format)
+    #[arg(long, default_value = "2.0")]
+    paragraph_gap_secs: f32,
+
     /// Path to write transcript
     #[arg(short, long)]
     write: Option<PathBuf>,
 
+    /// File name template of written transcripts (eg. `{stem}_{lang}_{timestamp}.{ext}`). placeholders: stem, lang,
+    /// timestamp, format and ext. relative to --write when it's a directory, otherwise to the input file directory
+    #[arg(long)]
+    write_pattern: Option<String>,
+
     /// Format of the transcript
     #[arg(short, long, default_value = "srt", value_parser = get_possible_formats())]
     // TODO: use possible values. confusing crate!
     format: String,
 
-    /// Format of the transcript
+    /// Pretty print JSON output (only with --format json)
+    #[arg(long)]
+    pretty: bool,
+
+    /// Place cues in a region at the bottom of the screen (only with --format vtt)
+    #[arg(long)]
+    vtt_regions: bool,
+
+    /// Enable word level timestamps. with --format ass every word is timed individually
     #[arg(long)]
-    // TODO: use possible values. confusing crate!
     word_timestamps: bool,
 
     /// Format of the transcript
@@ -106,6 +148,10 @@ struct Args {
     #[arg(long)]
     diarize: bool,
 
+    /// Label speaker turns with a tinydiarize model (eg. ggml-small.en-tdrz.bin). faster but less accurate than --diarize
+    #[arg(long, conflicts_with = "diarize")]
+    tdrz: bool,
+
     /// Path to vad model
     #[arg(long)]
     pub diarize_vad_model: Option<String>,
@@ -114,16 +160,119 @@ struct Args {
     #[arg(long)]
     diarize_speaker_id_model: Option<String>,
 
+    /// Watch directory and transcribe new audio files
+    #[arg(long)]
+    watch: Option<PathBuf>,
+
+    /// Glob pattern of files to transcribe in watch mode
+    #[arg(long, default_value = "*.{wav,mp3,ogg,flac,mp4}")]
+    watch_pattern: String,
+
+    /// Poll interval in milliseconds when native file events are unavailable
+    #[arg(long, default_value = "1000")]
+    watch_interval_ms: u64,
+
+    /// Use flash attention. requires a compatible model and GPU or BLAS build.
+    /// With --server it's the default of loaded models
+    #[arg(long)]
+    flash_attn: bool,
+
+    /// Align word timestamps with dynamic time warping. more accurate but slower, requires an official whisper model.
+    /// With --server it's the default of loaded models
+    #[arg(long)]
+    dtw_timestamps: bool,
+
     /// Run http server
     #[arg(long)]
     server: bool,
 
+    /// Measure transcription time of --file with --model and print the results as JSON
+    #[arg(long)]
+    benchmark: bool,
+
+    /// Number of transcriptions in benchmark
+    #[arg(long, default_value = "10")]
+    iterations: usize,
+
     #[arg(long, default_value = "0.0.0.0")]
     host: String,
 
     /// Port
     #[arg(long, default_value = "3022")]
     port: u16,
+
+    /// Expose server logs as server sent events in /logs/stream
+    #[arg(long)]
+    expose_logs: bool,
+
+    /// Header used to read and return the request id of server requests
+    #[arg(long, default_value = "X-Request-Id")]
+    request_id_header: String,
+
+    /// Number of model instances for transcribing server requests in parallel. each one uses the model memory
+    #[arg(long, default_value = "1")]
+    parallel_workers: usize,
+
+    /// Input formats accepted by the server
+    #[arg(long, value_delimiter = ',', default_value = "wav,mp3,ogg,flac,mp4,m4a,webm,mkv")]
+    supported_input_formats: Vec<String>,
+
+    /// Abort server transcriptions which take longer than the audio duration multiplied by this factor
+    #[arg(long)]
+    timeout_realtime_factor: Option<f64>,
+
+    /// Compress server responses with gzip or brotli when the client accepts it
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    compression_enabled: bool,
+
+    /// Compress only responses larger than this size
+    #[arg(long, default_value = "1024")]
+    compression_min_size_bytes: usize,
+
+    /// Log field names, sizes and text previews of server request bodies at debug level
+    #[arg(long)]
+    debug_log_requests: bool,
+
+    /// Log method, path, status, latency and client ip of every server request
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    access_log_enabled: bool,
+
+    /// Level of the access log events
+    #[arg(long, default_value = "info")]
+    access_log_level: String,
+
+    /// Skip the non fatal server startup checks (models, ffmpeg, disk space)
+    #[arg(long)]
+    skip_startup_checks: bool,
+
+    /// Serve on this unix domain socket in addition to TCP
+    #[arg(long)]
+    unix_socket_path: Option<PathBuf>,
+
+    /// Seconds between checks if the loaded model file changed on disk. 0 disables the check
+    #[arg(long, default_value = "3600")]
+    model_validation_interval_secs: u64,
+
+    /// Action when the loaded model file changed: warn, reload or error
+    #[arg(long, default_value = "warn")]
+    model_stale_action: String,
+
+    /// Seconds clients may cache the server status responses
+    #[arg(long, default_value = "2")]
+    status_cache_ttl_secs: u64,
+
+    /// OpenAI compatible chat completions URL for abstractive summaries in /summarize (eg. http://localhost:11434/v1/chat/completions).
+    /// Requires the `summarize` feature. without it summaries are extractive
+    #[arg(long)]
+    summarize_model_url: Option<String>,
+
+    /// Model name sent to the summarize model URL
+    #[arg(long, default_value = "llama3.1")]
+    summarize_model: String,
+
+    /// Warn when free GPU memory falls below this. checked every 30 seconds with the `cuda` feature
+    #[arg(long, default_value = "1.0")]
+    min_free_vram_gb: f32,
 }
 
 fn get_possible_languages() -> Vec<String> {
@@ -139,7 +288,78 @@ fn get_possible_languages() -> Vec<String> {
 }
 
 pub fn get_possible_formats() -> Vec<String> {
-    vec!["txt".into(), "srt".into(), "vtt".into()]
+    vec![
+        "txt".into(),
+        "srt".into(),
+        "vtt".into(),
+        "json".into(),
+        "ass".into(),
+        "audacity".into(),
+    ]
+}
+
+/// File extension of the output format
+fn format_extension(format: &str) -> &str {
+    match format {
+        "audacity" => "txt",
+        format => format,
+    }
+}
+
+/// Output path from the `--write-pattern` template
+fn format_output_path(pattern: &str, input: &Path, lang: Option<&str>, format: &str) -> Result<PathBuf> {
+    let mut output = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .context(format!("unclosed placeholder in write pattern {}", pattern))?;
+        let value = match &rest[start + 1..start + end] {
+            "stem" => input.file_stem().context("stem")?.to_string_lossy().to_string(),
+            "lang" => lang.unwrap_or("auto").to_string(),
+            // ISO 8601 without colons which are invalid in Windows file names
+            "timestamp" => chrono::Local::now().format("%Y-%m-%dT%H-%M-%S").to_string(),
+            "format" => format.to_string(),
+            "ext" => format_extension(format).to_string(),
+            placeholder => eyre::bail!(
+                "unknown placeholder {{{}}} in write pattern. expected stem, lang, timestamp, format or ext",
+                placeholder
+            ),
+        };
+        output.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(PathBuf::from(output))
+}
+
+/// JSON document printed by `--format json`. timestamps are in seconds
+fn transcript_to_json(transcript: &Transcript, pretty: bool) -> Result<String> {
+    let segments: Vec<Value> = transcript
+        .segments
+        .iter()
+        .map(|segment| {
+            json!({
+                "start": segment.start as f64 / 100.0,
+                "end": segment.stop as f64 / 100.0,
+                "text": segment.text.trim(),
+                "speaker": segment.speaker,
+            })
+        })
+        .collect();
+    let duration_secs = transcript.segments.last().map(|s| s.stop as f64 / 100.0).unwrap_or_default();
+    let value = json!({
+        "duration_secs": duration_secs,
+        "segments": segments,
+        "text": transcript.as_text().trim(),
+    });
+    let output = if pretty {
+        serde_json::to_string_pretty(&value)?
+    } else {
+        serde_json::to_string(&value)?
+    };
+    Ok(output)
 }
 
 fn prepare_model_path(path: &Path, app_handle: &tauri::AppHandle) -> Result<PathBuf> {
@@ -158,6 +378,172 @@ fn prepare_model_path(path: &Path, app_handle: &tauri::AppHandle) -> Result<Path
     Ok(path.to_path_buf())
 }
 
+/// Read stdin to a temp file. the file is removed when the returned path is dropped
+fn read_stdin_to_file(extension: &str) -> Result<TempPath> {
+    let mut file = tempfile::Builder::new()
+        .suffix(&format!(".{}", extension))
+        .tempfile()
+        .context("failed to create stdin temp file")?;
+    eprintln!("Reading audio from stdin...");
+    std::io::copy(&mut std::io::stdin().lock(), &mut file).context("failed to read stdin")?;
+    eprintln!("EOF received, starting transcription...");
+    Ok(file.into_temp_path())
+}
+
+fn format_transcript(transcript: &Transcript, args: &Args, options: &TranscribeOptions) -> Result<String> {
+    let output = match args.format.as_str() {
+        "srt" => transcript.as_srt(),
+        "vtt" => transcript.as_vtt(args.vtt_regions, options.vtt_style.as_ref()),
+        "txt" => post_process::text_with_paragraphs(transcript, args.paragraph_gap_secs),
+        "json" => transcript_to_json(transcript, args.pretty)?,
+        "ass" => transcript.as_ass(args.word_timestamps),
+        "audacity" => transcript.as_audacity_labels(),
+        _ => {
+            eprintln!("Invalid format specified. Defaulting to SRT format.");
+            transcript.as_srt()
+        }
+    };
+    Ok(output)
+}
+
+/// Wait until the file stops growing, new files are often still being written when created
+fn wait_for_file_ready(path: &Path, interval: Duration) -> Result<()> {
+    let mut prev_size = None;
+    loop {
+        let size = std::fs::metadata(path)?.len();
+        if prev_size == Some(size) {
+            return Ok(());
+        }
+        prev_size = Some(size);
+        std::thread::sleep(interval);
+    }
+}
+
+/// Transcribe new files in directory until interrupted. transcripts are written alongside the originals
+fn watch(dir: &Path, ctx: &WhisperContext, options: &TranscribeOptions, args: &Args) -> Result<()> {
+    let matcher = Glob::new(&args.watch_pattern)
+        .context(format!("invalid watch pattern {}", args.watch_pattern))?
+        .compile_matcher();
+    let interval = Duration::from_millis(args.watch_interval_ms);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher: Box<dyn Watcher> = match RecommendedWatcher::new(tx.clone(), notify::Config::default()) {
+        Ok(watcher) => Box::new(watcher),
+        Err(error) => {
+            tracing::debug!("native watcher unavailable ({:?}). fallback to polling", error);
+            Box::new(PollWatcher::new(tx, notify::Config::default().with_poll_interval(interval))?)
+        }
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    eprintln!("Watching {} for {} (Ctrl-C to stop) 👀", dir.display(), args.watch_pattern);
+
+    for event in rx {
+        let event = event?;
+        if !matches!(event.kind, EventKind::Create(_)) {
+            continue;
+        }
+        for path in event.paths {
+            let is_match = path.file_name().map(|name| matcher.is_match(name)).unwrap_or_default();
+            if !path.is_file() || !is_match {
+                continue;
+            }
+            eprintln!("Transcribe {}... 🔄", path.display());
+            let result = wait_for_file_ready(&path, interval).and_then(|_| {
+                let mut options = options.clone();
+                options.path = path.to_str().context("tostr")?.to_string();
+                let transcript = transcribe::transcribe(ctx, &options, None, None, None, None)?;
+                let output = format_transcript(&transcript, args, &options)?;
+                let output_dir = args.write.clone().filter(|p| p.is_dir());
+                let output_dir = output_dir.or(path.parent().map(Path::to_path_buf)).context("parent")?;
+                let file_name = match args.write_pattern.as_deref() {
+                    Some(pattern) => format_output_path(pattern, &path, options.lang.as_deref(), &args.format)?,
+                    None => {
+                        let stem = path.file_stem().context("stem")?.to_string_lossy();
+                        PathBuf::from(format!("{}.{}", stem, format_extension(&args.format)))
+                    }
+                };
+                let output_path = output_dir.join(file_name);
+                std::fs::write(&output_path, output)?;
+                Ok(output_path)
+            });
+            match result {
+                Ok(output_path) => eprintln!("{} -> {} ✅", path.display(), output_path.display()),
+                Err(error) => eprintln!("{} failed: {:?} ❌", path.display(), error),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// JSON Schema of the options file. nested schemas are referenced as in the OpenAPI document so they're included under `components`
+fn options_schema() -> Result<Value> {
+    let (_, schema) = TranscribeOptions::schema();
+    let mut schema = serde_json::to_value(schema)?;
+    let object = schema.as_object_mut().context("schema must be object")?;
+    // path comes from --file
+    if let Some(Value::Array(required)) = object.get_mut("required") {
+        required.retain(|field| field != "path");
+    }
+    object.insert("$schema".into(), json!("https://json-schema.org/draft/2020-12/schema"));
+    object.insert("title".into(), json!("vibe options file"));
+    let schemas: serde_json::Map<String, Value> = [
+        vibe_core::transcript::VttStyle::schema(),
+        vibe_core::pii_filter::PiiPattern::schema(),
+    ]
+    .into_iter()
+    .map(|(name, schema)| Ok((name.to_string(), serde_json::to_value(schema)?)))
+    .collect::<Result<_>>()?;
+    object.insert("components".into(), json!({ "schemas": schemas }));
+    Ok(schema)
+}
+
+/// Read options file. `path` is optional in the file since it comes from --file
+fn read_options_file(path: &Path) -> Result<TranscribeOptions> {
+    let content = std::fs::read_to_string(path).context(format!("failed to read options file {}", path.display()))?;
+    let mut value: Value = serde_json::from_str(&content).context("options file is not valid JSON")?;
+    let object = value.as_object_mut().context("options file must contain JSON object")?;
+    object.entry("path").or_insert(json!(""));
+    serde_json::from_value(value).context("invalid options file")
+}
+
+/// Merge CLI flags into the options. flags with default values override only when passed explicitly
+fn merge_cli_options(mut options: TranscribeOptions, args: &Args, matches: &ArgMatches, lang: String) -> TranscribeOptions {
+    let is_explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if is_explicit("language") || options.lang.is_none() {
+        options.lang = Some(lang);
+    }
+    if is_explicit("n_threads") || options.n_threads.is_none() {
+        options.n_threads = args.n_threads;
+    }
+    if is_explicit("temperature") || options.temperature.is_none() {
+        options.temperature = args.temperature;
+    }
+    if args.init_prompt.is_some() {
+        options.init_prompt = args.init_prompt.clone();
+    }
+    if args.max_text_ctx.is_some() {
+        options.max_text_ctx = args.max_text_ctx;
+    }
+    if args.max_sentence_len.is_some() {
+        options.max_sentence_len = args.max_sentence_len;
+    }
+    if args.flash_attn {
+        options.flash_attn = Some(true);
+    }
+    if args.dtw_timestamps {
+        options.dtw_timestamps = Some(true);
+    }
+    if args.tdrz {
+        options.tdrz = Some(true);
+    }
+    if args.word_timestamps || options.word_timestamps.is_none() {
+        options.word_timestamps = Some(args.word_timestamps);
+    }
+    if options.verbose.is_none() {
+        options.verbose = Some(false);
+    }
+    options
+}
+
 fn language_name_to_whisper_lang(name: &str) -> Result<String> {
     let languages_json = include_str!("../../src/assets/whisper-languages.json");
     let languages: Value = serde_json::from_str(languages_json).context("tostr")?;
@@ -169,7 +555,8 @@ pub async fn run(app_handle: &AppHandle) -> Result<()> {
     crate::dock::set_dock_visible(false);
 
     #[allow(unused_mut)]
-    let mut args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches)?;
 
     if args.diarize && args.diarize_vad_model.is_none() {
         panic!("Please provide model path with --diarize-vad-model")
@@ -180,63 +567,145 @@ pub async fn run(app_handle: &AppHandle) -> Result<()> {
         args.format = "json".into();
     }
 
+    if args.config_schema {
+        println!("{}", serde_json::to_string_pretty(&options_schema()?)?);
+        app_handle.cleanup_before_exit();
+        process::exit(0);
+    }
+
     if args.server {
-        server::run(app_handle.clone(), args.host, args.port).await?;
+        #[cfg(not(feature = "summarize"))]
+        if args.summarize_model_url.is_some() {
+            eyre::bail!("--summarize-model-url requires the summarize feature");
+        }
+        let model_path = args
+            .model
+            .as_ref()
+            .map(|model| prepare_model_path(model, app_handle))
+            .transpose()?;
+        startup_checks::startup_checks(
+            &get_models_folder(app_handle.clone())?,
+            model_path.as_deref(),
+            args.skip_startup_checks,
+        )?;
+        let server_options = server::ServerOptions {
+            host: args.host.clone(),
+            port: args.port,
+            expose_logs: args.expose_logs,
+            request_id_header: args.request_id_header.clone(),
+            parallel_workers: args.parallel_workers,
+            supported_input_formats: args.supported_input_formats.clone(),
+            timeout_realtime_factor: args.timeout_realtime_factor,
+            compression_enabled: args.compression_enabled,
+            compression_min_size_bytes: args.compression_min_size_bytes,
+            debug_log_requests: args.debug_log_requests,
+            access_log_enabled: args.access_log_enabled,
+            access_log_level: args.access_log_level.clone(),
+            unix_socket_path: args.unix_socket_path.clone(),
+            model_validation_interval_secs: args.model_validation_interval_secs,
+            model_stale_action: args.model_stale_action.clone(),
+            status_cache_ttl_secs: args.status_cache_ttl_secs,
+            flash_attn: args.flash_attn,
+            dtw_timestamps: args.dtw_timestamps,
+            summarize_model_url: args.summarize_model_url.clone(),
+            summarize_model: args.summarize_model.clone(),
+            min_free_vram_gb: args.min_free_vram_gb,
+        };
+        server::run(app_handle.clone(), server_options).await?;
     }
     let lang = language_name_to_whisper_lang(&args.language)?;
-    let options = TranscribeOptions {
-        path: args.file.context("file")?,
-        lang: Some(lang),
-        init_prompt: args.init_prompt,
-        n_threads: args.n_threads,
-        temperature: args.temperature,
-        translate: args.translate,
-        verbose: Some(false),
-        max_text_ctx: args.max_text_ctx,
-        word_timestamps: Some(args.word_timestamps),
-        max_sentence_len: args.max_sentence_len,
+    let mut file = if args.watch.is_some() {
+        String::new()
+    } else {
+        args.file.clone().context("file")?
     };
-    let model_path = prepare_model_path(&args.model.context("model")?, app_handle)?;
+    let stdin_path = if file == "-" {
+        let path = read_stdin_to_file(&args.stdin_format)?;
+        file = path.to_str().context("tostr")?.to_string();
+        Some(path)
+    } else {
+        None
+    };
+    // Whisper can translate only to English by itself
+    let translate_to = args.translate_to.clone().filter(|target| !target.is_empty());
+    let translate_with_whisper = translate_to.as_deref().map(|target| target == "en");
+    #[cfg(not(feature = "translate"))]
+    if translate_with_whisper == Some(false) {
+        eyre::bail!("Translate to languages other than English requires the translate feature");
+    }
+    let options = match args.options_file.as_deref() {
+        Some(path) => read_options_file(path)?,
+        None => TranscribeOptions::default(),
+    };
+    let mut options = merge_cli_options(options, &args, &matches, lang);
+    options.path = file;
+    if let Some(translate) = translate_with_whisper.or(args.translate) {
+        options.translate = Some(translate);
+    }
+    let model_path = prepare_model_path(&args.model.clone().context("model")?, app_handle)?;
+
+    // Fail on invalid write pattern before transcribing
+    if let Some(pattern) = args.write_pattern.as_deref() {
+        format_output_path(pattern, Path::new("input.wav"), None, &args.format)?;
+    }
+
+    if args.benchmark {
+        let result = bench::benchmark(&model_path, &options, args.iterations)?;
+        println!("{}", serde_json::to_string(&result)?);
+        // process::exit doesn't run destructors
+        drop(stdin_path);
+        app_handle.cleanup_before_exit();
+        process::exit(0);
+    }
+
+    if let Some(watch_dir) = args.watch.as_deref() {
+        let ctx = transcribe::create_context(&model_path, ContextOptions::default().with_overrides(&options))?;
+        watch(watch_dir, &ctx, &options, &args)?;
+        app_handle.cleanup_before_exit();
+        process::exit(0);
+    }
 
     eprintln!("Transcribe... 🔄");
     let start = Instant::now(); // Measure start time
-    let ctx = transcribe::create_context(&model_path, None)?;
+    let ctx = transcribe::create_context(&model_path, ContextOptions::default().with_overrides(&options))?;
     #[allow(unused_mut)]
     let mut transcript = transcribe::transcribe(&ctx, &options, None, None, None, None)?;
 
+    #[cfg(feature = "translate")]
+    if let Some(target) = translate_to.filter(|target| target != "en") {
+        eprintln!("Translate to {}... 🔄", target);
+        let source = options.lang.clone().unwrap_or("auto".into());
+        vibe_core::translate::translate_transcript(&mut transcript, &source, &target).await?;
+    }
+
     let elapsed = start.elapsed();
-    println!(
-        "{}",
-        match args.format.as_str() {
-            "srt" => transcript.as_srt(),
-            "vtt" => transcript.as_vtt(),
-            "txt" => transcript.as_text(),
-            "json" => transcript.as_json()?,
-            _ => {
-                eprintln!("Invalid format specified. Defaulting to SRT format.");
-                transcript.as_srt()
-            }
+    let output = format_transcript(&transcript, &args, &options)?;
+    println!("{}", output);
+
+    // Write transcript if write path or pattern is provided
+    if let Some(pattern) = args.write_pattern.as_deref() {
+        let input = Path::new(&options.path);
+        let output_dir = match args.write.clone().filter(|p| p.is_dir()) {
+            Some(dir) => dir,
+            None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        let write_path = output_dir.join(format_output_path(pattern, input, options.lang.as_deref(), &args.format)?);
+        if let Err(err) = std::fs::write(&write_path, output) {
+            eprintln!("Error writing transcript to {}: {}", write_path.display(), err);
         }
-    );
-
-    // Write transcript if write path is provided
-    if let Some(write_path) = args.write {
-        if let Err(err) = std::fs::write(
-            write_path,
-            match args.format.as_str() {
-                "srt" => transcript.as_srt(),
-                "vtt" => transcript.as_vtt(),
-                "txt" => transcript.as_text(),
-                _ => {
-                    eprintln!("Invalid format specified. Defaulting to SRT format.");
-                    transcript.as_srt()
-                }
-            },
-        ) {
+    } else if let Some(write_path) = args.write.as_ref() {
+        // Add extension of the format when missing
+        let write_path = if write_path.extension().is_none() {
+            write_path.with_extension(format_extension(&args.format))
+        } else {
+            write_path.clone()
+        };
+        if let Err(err) = std::fs::write(write_path, output) {
             eprintln!("Error writing transcript to file: {}", err);
         }
     }
 
+    drop(stdin_path);
     app_handle.cleanup_before_exit();
     eprintln!(
         "Transcription completed in {:.1}s ⏱️",
@@ -245,3 +714,71 @@ pub async fn run(app_handle: &AppHandle) -> Result<()> {
     eprintln!("Done ✅");
     process::exit(0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vibe_core::transcript::Segment;
+
+    #[test]
+    fn test_options_schema() {
+        let schema = options_schema().unwrap();
+        let required = schema["required"].as_array().cloned().unwrap_or_default();
+        assert!(!required.contains(&json!("path")));
+        assert!(schema["properties"]["lang"].is_object());
+        assert!(schema["components"]["schemas"]["VttStyle"].is_object());
+        assert!(schema["components"]["schemas"]["PiiPattern"].is_object());
+        // Printed schema parses back
+        let parsed: Value = serde_json::from_str(&serde_json::to_string_pretty(&schema).unwrap()).unwrap();
+        assert_eq!(parsed, schema);
+    }
+
+    #[test]
+    fn test_format_output_path() {
+        let input = Path::new("/audio/meeting.mp3");
+        let path = format_output_path("{stem}_{lang}.{ext}", input, Some("en"), "srt").unwrap();
+        assert_eq!(path, PathBuf::from("meeting_en.srt"));
+        let path = format_output_path("out/{stem}-{format}.{ext}", input, None, "json").unwrap();
+        assert_eq!(path, PathBuf::from("out/meeting-json.json"));
+        let path = format_output_path("{lang}", input, None, "srt").unwrap();
+        assert_eq!(path, PathBuf::from("auto"));
+        assert!(format_output_path("{stem}_{timestamp}", input, None, "srt")
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("meeting_"));
+        assert!(format_output_path("{name}.{ext}", input, None, "srt").is_err());
+        assert!(format_output_path("{stem", input, None, "srt").is_err());
+    }
+
+    #[test]
+    fn test_transcript_to_json() {
+        let transcript = Transcript {
+            segments: vec![
+                Segment {
+                    start: 0,
+                    stop: 150,
+                    text: " Hello".into(),
+                    speaker: Some("0".into()),
+                    ..Default::default()
+                },
+                Segment {
+                    start: 150,
+                    stop: 320,
+                    text: " world".into(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let output = transcript_to_json(&transcript, false).unwrap();
+        assert!(!output.contains('\n'));
+        let value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["duration_secs"], 3.2);
+        assert_eq!(value["segments"][0]["start"], 0.0);
+        assert_eq!(value["segments"][0]["end"], 1.5);
+        assert_eq!(value["segments"][0]["text"], "Hello");
+        assert_eq!(value["segments"][0]["speaker"], "0");
+        assert!(value["segments"][1]["speaker"].is_null());
+        assert!(transcript_to_json(&transcript, true).unwrap().contains('\n'));
+    }
+}
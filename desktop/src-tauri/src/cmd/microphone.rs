@@ -0,0 +1,200 @@
+use crate::cmd::DiarizeOptions;
+use crate::config::STORE_FILENAME;
+use crate::setup::ModelContext;
+use crate::utils::{random_string, LogError};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, Stream};
+use eyre::{bail, eyre, Context, ContextCompat, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State, Wry};
+use tauri_plugin_store::{with_store, StoreCollection};
+use vibe_core::config::TranscribeOptions;
+use vibe_core::transcript::Transcript;
+
+type WavWriterHandle = Arc<Mutex<Option<hound::WavWriter<BufWriter<File>>>>>;
+
+/// Interval of `recording-level` events
+const LEVEL_INTERVAL_MS: u32 = 100;
+
+struct StreamHandle(Stream);
+unsafe impl Send for StreamHandle {}
+unsafe impl Sync for StreamHandle {}
+
+pub struct Recording {
+    stream: StreamHandle,
+    writer: WavWriterHandle,
+    path: PathBuf,
+}
+
+/// Active microphone recording
+pub type RecordingState = Mutex<Option<Recording>>;
+
+/// Accumulate samples and emit their RMS in dB every `LEVEL_INTERVAL_MS`
+struct LevelMeter {
+    app_handle: AppHandle,
+    samples_per_interval: usize,
+    sum_squares: f64,
+    count: usize,
+}
+
+impl LevelMeter {
+    fn push(&mut self, sample: f32) {
+        self.sum_squares += (sample as f64) * (sample as f64);
+        self.count += 1;
+        if self.count >= self.samples_per_interval {
+            let rms = (self.sum_squares / self.count as f64).sqrt();
+            let db = 20.0 * rms.max(1e-10).log10();
+            self.app_handle
+                .emit("recording-level", db)
+                .map_err(|e| eyre!("{:?}", e))
+                .log_error();
+            self.sum_squares = 0.0;
+            self.count = 0;
+        }
+    }
+}
+
+/// Device name from `recording_device` in the store
+fn get_recording_device_name(app_handle: &AppHandle) -> Option<String> {
+    let stores = app_handle.state::<StoreCollection<Wry>>();
+    with_store(app_handle.clone(), stores, STORE_FILENAME, |store| {
+        Ok(store
+            .get("recording_device")
+            .and_then(|name| name.as_str().map(str::to_string)))
+    })
+    .ok()
+    .flatten()
+}
+
+fn write_samples<T>(input: &[T], writer: &WavWriterHandle, level_meter: &Mutex<LevelMeter>)
+where
+    T: Sample + hound::Sample + Copy,
+    f32: FromSample<T>,
+{
+    if let Ok(mut guard) = writer.try_lock() {
+        if let Some(writer) = guard.as_mut() {
+            for &sample in input.iter() {
+                writer.write_sample(sample).ok();
+            }
+        }
+    }
+    if let Ok(mut level_meter) = level_meter.lock() {
+        for &sample in input.iter() {
+            level_meter.push(f32::from_sample(sample));
+        }
+    }
+}
+
+#[tauri::command]
+/// Record from the microphone (`recording_device` or default input) into temp wav file
+pub fn start_recording(app_handle: AppHandle, recording_state: State<'_, RecordingState>) -> Result<()> {
+    let mut recording = recording_state.lock().map_err(|e| eyre!("{:?}", e))?;
+    if recording.is_some() {
+        bail!("Recording already started")
+    }
+
+    let host = cpal::default_host();
+    let device_name = get_recording_device_name(&app_handle);
+    let device = match device_name {
+        Some(name) => host
+            .input_devices()?
+            .find(|device| device.name().map(|n| n == name).unwrap_or_default())
+            .context(format!("Recording device {} not found", name))?,
+        None => host.default_input_device().context("No default input device")?,
+    };
+    tracing::debug!("Recording from microphone: {:?}", device.name());
+
+    let config = device.default_input_config().context("Failed to get default input config")?;
+    let spec = hound::WavSpec {
+        channels: config.channels() as _,
+        sample_rate: config.sample_rate().0 as _,
+        bits_per_sample: (config.sample_format().sample_size() * 8) as _,
+        sample_format: if config.sample_format().is_float() {
+            hound::SampleFormat::Float
+        } else {
+            hound::SampleFormat::Int
+        },
+    };
+    let path = std::env::temp_dir().join(format!("{}.wav", random_string(10)));
+    tracing::debug!("WAV file path: {:?}", path);
+    let writer: WavWriterHandle = Arc::new(Mutex::new(Some(hound::WavWriter::create(&path, spec)?)));
+    let samples_per_interval = (spec.sample_rate * spec.channels as u32 * LEVEL_INTERVAL_MS / 1000) as usize;
+    let level_meter = Arc::new(Mutex::new(LevelMeter {
+        app_handle: app_handle.clone(),
+        samples_per_interval,
+        sum_squares: 0.0,
+        count: 0,
+    }));
+
+    let err_fn = move |err| {
+        tracing::error!("An error occurred on stream: {}", err);
+    };
+    let writer_c = writer.clone();
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_samples::<i16>(data, &writer_c, &level_meter),
+            err_fn,
+            None,
+        )?,
+        cpal::SampleFormat::I32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_samples::<i32>(data, &writer_c, &level_meter),
+            err_fn,
+            None,
+        )?,
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &config.into(),
+            move |data, _: &_| write_samples::<f32>(data, &writer_c, &level_meter),
+            err_fn,
+            None,
+        )?,
+        sample_format => {
+            bail!("Unsupported sample format '{}'", sample_format)
+        }
+    };
+    stream.play()?;
+
+    *recording = Some(Recording {
+        stream: StreamHandle(stream),
+        writer,
+        path,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+/// Stop the microphone recording and transcribe it
+pub async fn stop_recording(
+    app_handle: AppHandle,
+    recording_state: State<'_, RecordingState>,
+    model_context_state: State<'_, tokio::sync::Mutex<Option<ModelContext>>>,
+    mut options: TranscribeOptions,
+    diarize_options: DiarizeOptions,
+) -> Result<Transcript> {
+    let recording = recording_state
+        .lock()
+        .map_err(|e| eyre!("{:?}", e))?
+        .take()
+        .context("Recording not started")?;
+    recording.stream.0.pause()?;
+    drop(recording.stream);
+    let writer = recording
+        .writer
+        .lock()
+        .map_err(|e| eyre!("{:?}", e))?
+        .take()
+        .context("writer")?;
+    writer.finalize()?;
+    tracing::debug!("Recording saved to {}", recording.path.display());
+
+    options.path = recording.path.to_str().context("tostr")?.to_string();
+    let result = super::transcribe(app_handle, options, model_context_state, diarize_options).await;
+    std::fs::remove_file(&recording.path)
+        .context("Failed to remove recording")
+        .log_error();
+    result
+}
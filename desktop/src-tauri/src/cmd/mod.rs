@@ -1,5 +1,5 @@
 use crate::config::{DEAFULT_MODEL_FILENAME, DEAFULT_MODEL_URL, STORE_FILENAME};
-use crate::setup::ModelContext;
+use crate::setup::{DownloadRegistry, ModelContext};
 use crate::utils::{get_current_dir, LogError};
 use eyre::{bail, eyre, Context, ContextCompat, OptionExt, Result};
 use serde::{Deserialize, Serialize};
@@ -17,9 +17,12 @@ use tauri::{
 use tauri::{Emitter, Listener, State, Wry};
 use tauri_plugin_store::{with_store, StoreCollection};
 use tokio::sync::Mutex;
+use vibe_core::transcribe::ContextOptions;
 use vibe_core::transcript::Segment;
 use vibe_core::transcript::Transcript;
 pub mod audio;
+#[cfg(feature = "microphone")]
+pub mod microphone;
 
 /// Return true if there's internet connection
 /// timeout in ms
@@ -82,6 +85,7 @@ pub fn get_x86_features() -> Option<Value> {
     }
 }
 #[tauri::command]
+#[tracing::instrument(skip(app_handle))]
 pub async fn download_model(app_handle: tauri::AppHandle, url: Option<String>) -> Result<String> {
     let model_path = if let Some(url) = url.clone() {
         let filename = vibe_core::downloader::get_filename(&url).await?;
@@ -106,12 +110,20 @@ pub async fn download_model(app_handle: tauri::AppHandle, url: Option<String>) -
         abort_atomic_c.store(true, Ordering::Relaxed);
     });
 
+    let model_name = model_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().trim_start_matches("ggml-").to_string())
+        .unwrap_or_default();
+    let download_id = app_handle.state::<DownloadRegistry>().start(model_name);
+
     let download_progress_callback = {
         let app_handle = app_handle.clone();
         let abort_atomic = abort_atomic.clone();
+        let download_id = download_id.clone();
 
         move |current: u64, total: u64| {
             let app_handle = app_handle.clone();
+            app_handle.state::<DownloadRegistry>().update(&download_id, current, total);
 
             // Update progress in background
             tauri::async_runtime::spawn(async move {
@@ -137,14 +149,18 @@ pub async fn download_model(app_handle: tauri::AppHandle, url: Option<String>) -
         DEAFULT_MODEL_URL.to_string()
     };
 
-    downloader
+    let result = downloader
         .download(&download_url, model_path.to_owned(), download_progress_callback)
-        .await?;
+        .await;
+    let status = if result.is_ok() { "completed" } else { "failed" };
+    app_handle.state::<DownloadRegistry>().finish(&download_id, status);
+    result?;
     set_progress_bar(&app_handle_c, None)?;
     Ok(model_path.to_str().context("to_str")?.to_string())
 }
 
 #[tauri::command]
+#[tracing::instrument(skip(app_handle))]
 pub async fn download_file(app_handle: tauri::AppHandle, url: String, path: String) -> Result<()> {
     let mut downloader = vibe_core::downloader::Downloader::new();
     tracing::debug!("Download model invoked! with path {}", path);
@@ -215,7 +231,23 @@ pub async fn transcribe(
     if model_context.is_none() {
         bail!("Please load model first")
     }
+    if model_context.as_ref().is_some_and(|ctx| ctx.stale) {
+        bail!("Model file changed on disk. Please load the model again")
+    }
     let ctx = model_context.as_ref().context("as ref")?;
+    // Other context options use a context of this request only. the loaded context keeps the options it was loaded
+    // with, same as the worker pool contexts
+    let context_options = ctx.options.with_overrides(&options);
+    let request_handle = if context_options != ctx.options {
+        tracing::debug!(
+            "context options {:?} differ from the loaded model. creating request context",
+            context_options
+        );
+        Some(vibe_core::transcribe::create_context(Path::new(&ctx.path), context_options)?)
+    } else {
+        None
+    };
+    let handle = request_handle.as_ref().unwrap_or(&ctx.handle);
     let app_handle_c = app_handle.clone();
 
     let new_segment_callback = move |segment: Segment| {
@@ -268,7 +300,7 @@ pub async fn transcribe(
     }
     let unwind_result = catch_unwind(AssertUnwindSafe(|| {
         vibe_core::transcribe::transcribe(
-            &ctx.handle,
+            handle,
             &options,
             Some(Box::new(progress_callback)),
             Some(Box::new(new_segment_callback)),
@@ -371,28 +403,30 @@ pub fn is_avx2_enabled() -> bool {
 
 #[tauri::command]
 pub async fn load_model(app_handle: tauri::AppHandle, model_path: String, gpu_device: Option<i32>) -> Result<String> {
+    let options = ContextOptions {
+        gpu_device,
+        ..Default::default()
+    };
+    load_model_with_options(app_handle, model_path, options).await
+}
+
+pub async fn load_model_with_options(
+    app_handle: tauri::AppHandle,
+    model_path: String,
+    options: ContextOptions,
+) -> Result<String> {
     let model_context_state: State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
     let mut state_guard = model_context_state.lock().await;
     if let Some(state) = state_guard.as_ref() {
         // check if new path is different
-        if model_path != state.path || gpu_device != state.gpu_device {
-            tracing::debug!("model path or gpu device changed. reloading");
+        if model_path != state.path || options != state.options || state.stale {
+            tracing::debug!("model path or context options changed. reloading");
             // reload
-            let context = vibe_core::transcribe::create_context(Path::new(&model_path), gpu_device)?;
-            *state_guard = Some(ModelContext {
-                path: model_path.clone(),
-                handle: context,
-                gpu_device,
-            });
+            *state_guard = Some(ModelContext::new(&model_path, options)?);
         }
     } else {
         tracing::debug!("loading model first time");
-        let context = vibe_core::transcribe::create_context(Path::new(&model_path), gpu_device)?;
-        *state_guard = Some(ModelContext {
-            path: model_path.clone(),
-            handle: context,
-            gpu_device,
-        });
+        *state_guard = Some(ModelContext::new(&model_path, options)?);
     }
     Ok(model_path)
 }
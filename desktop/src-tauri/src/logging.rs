@@ -1,13 +1,55 @@
 use chrono::Local;
 use eyre::{Context, Result};
-use serde_json::Value;
+use once_cell::sync::Lazy;
+use serde_json::{json, Map, Value};
 use std::{fs::OpenOptions, path::PathBuf};
 use tauri::{AppHandle, Manager, Wry};
 use tauri_plugin_store::Store;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Layer, Registry};
 
 use crate::{cmd::is_portable, config, utils::get_current_dir};
 
+/// Number of log lines kept for slow subscribers of the log stream
+const LOG_STREAM_BUFFER: usize = 1000;
+
+/// Formatted json log lines for `/logs/stream`
+pub static LOG_STREAM: Lazy<broadcast::Sender<String>> = Lazy::new(|| broadcast::channel(LOG_STREAM_BUFFER).0);
+
+struct JsonVisitor(Map<String, Value>);
+
+impl Visit for JsonVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::String(value.to_string()));
+    }
+}
+
+/// Layer that writes every event as json line to `LOG_STREAM`
+struct BroadcastLayer;
+
+impl<S: tracing::Subscriber> Layer<S> for BroadcastLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        if LOG_STREAM.receiver_count() == 0 {
+            return;
+        }
+        let mut visitor = JsonVisitor(Map::new());
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let line = json!({
+            "timestamp": Local::now().to_rfc3339(),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "fields": visitor.0,
+        });
+        let _ = LOG_STREAM.send(line.to_string());
+    }
+}
+
 pub fn get_log_path(app: &AppHandle) -> Result<PathBuf> {
     let config_path = if is_portable() {
         get_current_dir()?
@@ -24,11 +66,13 @@ pub fn get_log_path(app: &AppHandle) -> Result<PathBuf> {
 }
 
 pub fn setup_logging(app: &AppHandle, store: Store<Wry>) -> Result<()> {
-    let sub = Registry::default().with(
-        tracing_subscriber::fmt::layer()
-            .with_ansi(true)
-            .with_filter(EnvFilter::from_default_env()),
-    );
+    let sub = Registry::default()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(true)
+                .with_filter(EnvFilter::from_default_env()),
+        )
+        .with(BroadcastLayer);
 
     if store
         .get("prefs_log_to_file")
@@ -1,16 +1,28 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod access_log;
+mod bench;
+mod circuit_breaker;
 mod cli;
 mod cmd;
 mod config;
+mod memory_tracker;
 mod panic_hook;
+mod request_id;
+mod request_log;
 mod server;
 mod setup;
+mod startup_checks;
+mod stats;
 mod utils;
+mod validation;
 use tauri::{Emitter, Manager};
 mod logging;
 
+#[cfg(feature = "prompt-tuning")]
+mod prompt_library;
+
 #[cfg(target_os = "macos")]
 mod dock;
 
@@ -81,6 +93,10 @@ fn main() -> Result<()> {
             cmd::get_models_folder,
             cmd::is_portable,
             cmd::get_logs_folder,
+            #[cfg(feature = "microphone")]
+            cmd::microphone::start_recording,
+            #[cfg(feature = "microphone")]
+            cmd::microphone::stop_recording,
             #[cfg(windows)]
             cmd::set_high_gpu_preference
         ])
@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use sysinfo::{ProcessesToUpdate, System};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn process_memory(system: &mut System, pid: sysinfo::Pid) -> Option<u64> {
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]));
+    system.process(pid).map(|process| process.memory())
+}
+
+/// Track peak resident memory of the process while running
+pub struct MemoryTracker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Option<u64>>>,
+}
+
+impl MemoryTracker {
+    pub fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_c = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let pid = sysinfo::get_current_pid().ok()?;
+            let mut system = System::new();
+            let baseline = process_memory(&mut system, pid)?;
+            let mut peak = baseline;
+            while !stop_c.load(Ordering::Relaxed) {
+                std::thread::sleep(POLL_INTERVAL);
+                if let Some(memory) = process_memory(&mut system, pid) {
+                    peak = peak.max(memory);
+                }
+            }
+            Some(peak.saturating_sub(baseline))
+        });
+        Self {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Peak increase in bytes since start. None when not supported on the platform
+    pub fn stop(mut self) -> Option<u64> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.take()?.join().ok().flatten()
+    }
+}
+
+impl Drop for MemoryTracker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,30 @@
+use eyre::{bail, Context, Result};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct PromptFile {
+    prompts: Vec<String>,
+}
+
+/// Built in prompt library of `/fine_tune_prompt`. an empty prompt is the baseline without init_prompt
+fn library_source(domain: &str) -> Option<&'static str> {
+    match domain {
+        "general" => Some(include_str!("../prompts/general.toml")),
+        "medical" => Some(include_str!("../prompts/medical.toml")),
+        "legal" => Some(include_str!("../prompts/legal.toml")),
+        "technical" => Some(include_str!("../prompts/technical.toml")),
+        _ => None,
+    }
+}
+
+/// Candidate prompts of the domain
+pub fn candidate_prompts(domain: &str) -> Result<Vec<String>> {
+    let Some(source) = library_source(domain) else {
+        bail!("unknown domain {}. use medical, legal, technical or general", domain);
+    };
+    let file: PromptFile = toml::from_str(source).context(format!("invalid prompt library {}", domain))?;
+    if file.prompts.is_empty() {
+        bail!("prompt library {} is empty", domain);
+    }
+    Ok(file.prompts)
+}
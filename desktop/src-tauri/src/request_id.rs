@@ -0,0 +1,41 @@
+use axum::extract::{Request, State};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use rand::Rng;
+use tracing::Instrument;
+
+/// Random UUID v4
+fn generate_request_id() -> String {
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Read request id from the header or generate one. logs inside the request are recorded in a span with the request id
+/// and the id is returned in the same response header
+pub async fn propagate_request_id(State(header_name): State<HeaderName>, request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&header_name)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .unwrap_or_else(generate_request_id);
+
+    let span = tracing::info_span!("request", request_id = %request_id, method = %request.method(), uri = %request.uri());
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(header_name, value);
+    }
+    response
+}
@@ -0,0 +1,95 @@
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+
+/// Bytes of text fields included in the log
+const PREVIEW_BYTES: usize = 200;
+const REDACTED_FIELDS: [&str; 2] = ["api_key", "token"];
+/// Larger bodies are logged by size only, so uploads are never buffered in memory
+const MAX_INSPECTED_BODY_BYTES: usize = 64 * 1024;
+
+fn preview(text: &str) -> &str {
+    let mut end = text.len().min(PREVIEW_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn log_fields(prefix: &str, fields: &serde_json::Map<String, Value>) {
+    for (name, value) in fields {
+        let field_name = format!("{}{}", prefix, name);
+        let size_bytes = value.to_string().len();
+        match value {
+            _ if REDACTED_FIELDS.contains(&name.as_str()) => {
+                tracing::debug!(field_name = %field_name, size_bytes, "request field: [redacted]");
+            }
+            Value::Object(fields) => {
+                tracing::debug!(field_name = %field_name, content_type = "object", size_bytes, "request field");
+                log_fields(&format!("{}.", field_name), fields);
+            }
+            Value::String(text) => {
+                tracing::debug!(field_name = %field_name, content_type = "string", size_bytes, "request field: {}", preview(text));
+            }
+            value => {
+                tracing::debug!(field_name = %field_name, size_bytes, "request field: {}", preview(&value.to_string()));
+            }
+        }
+    }
+}
+
+/// Log a multipart form field. file fields are logged without `text`
+pub fn log_multipart_field(name: &str, content_type: Option<&str>, size_bytes: usize, text: Option<&str>) {
+    let content_type = content_type.unwrap_or("text/plain");
+    match text {
+        _ if REDACTED_FIELDS.contains(&name) => {
+            tracing::debug!(field_name = %name, content_type, size_bytes, "request field: [redacted]");
+        }
+        Some(text) => {
+            tracing::debug!(field_name = %name, content_type, size_bytes, "request field: {}", preview(text));
+        }
+        None => tracing::debug!(field_name = %name, content_type, size_bytes, "request field"),
+    }
+}
+
+/// Log field names, sizes and text previews of request bodies to debug malformed client requests.
+/// Only JSON bodies up to `MAX_INSPECTED_BODY_BYTES` are read, others are logged by content type and size
+/// and passed to the handler untouched. multipart fields are logged by the handlers
+pub async fn log_request_fields(request: Request, next: Next) -> Response {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    let inspect =
+        content_type.starts_with("application/json") && content_length.is_some_and(|length| length <= MAX_INSPECTED_BODY_BYTES);
+    if !inspect {
+        if let Some(size_bytes) = content_length.filter(|length| *length > 0) {
+            tracing::debug!(content_type = %content_type, size_bytes, "request body");
+        }
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_INSPECTED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            tracing::debug!("failed to read request body: {:?}", error);
+            return (StatusCode::BAD_REQUEST, "failed to read request body").into_response();
+        }
+    };
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(fields)) => log_fields("", &fields),
+        _ if !bytes.is_empty() => tracing::debug!(content_type = %content_type, size_bytes = bytes.len(), "request body"),
+        _ => {}
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
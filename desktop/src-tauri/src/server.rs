@@ -1,48 +1,639 @@
+use crate::access_log::AccessLog;
+use crate::circuit_breaker::{DownloadCircuitBreakers, DownloadPermit};
 use crate::cmd::{self, DiarizeOptions};
-use crate::setup::ModelContext;
-use axum::extract::State;
-use axum::http::StatusCode;
-use axum::response::Result;
+use crate::logging;
+use crate::memory_tracker::MemoryTracker;
+use crate::request_id;
+use crate::request_log;
+use crate::setup::{model_modified, DownloadRegistry, ModelContext, SystemStats};
+use crate::stats::{Stats, StatsCollector, StatsWindow};
+use crate::utils::{random_string, LogError};
+use crate::validation::{self, ValidJson};
+use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response, Result};
 use axum::routing::post;
 use axum::Json;
 use axum::{routing::get, Router};
 use eyre::eyre;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::Infallible;
+use std::future::IntoFuture;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tauri::Manager;
-use tokio::sync::Mutex;
+use tokio::sync::broadcast;
+use tokio::sync::{Mutex, Notify, Semaphore};
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+use tower_http::trace::TraceLayer;
+use tracing::Instrument;
 use utoipa::{OpenApi, ToSchema};
 use utoipa_swagger_ui::SwaggerUi;
-use vibe_core::config::TranscribeOptions;
-use vibe_core::transcript::{Segment, Transcript};
+use validator::Validate;
+use vibe_core::config::{ConfigWarning, ConfigWarningKind, TranscribeOptions};
+use vibe_core::diff::{Change, ChangeType, WordDiff};
+use vibe_core::error::TranscribeError;
+use vibe_core::gpu::GpuDevice;
+use vibe_core::merge::MergedTranscript;
+use vibe_core::pii_filter::{PiiEntityCount, PiiPattern};
+use vibe_core::search::{SearchMatch, SearchOptions, SearchSegment};
+use vibe_core::summarize::{SummarizeOptions, Summary, SummaryStyle};
+use vibe_core::transcribe::ContextOptions;
+use vibe_core::transcript::{AudioMeta, Segment, Token, Transcript};
 
 #[derive(OpenApi)]
 #[openapi(
-    paths(list_models, load, transcribe),
-    components(schemas(TranscribeOptions, LoadPayload, Transcript, Segment))
+    paths(
+        health,
+        gpu_info,
+        stats,
+        list_models,
+        download_status,
+        load,
+        load_status,
+        evict_model,
+        transcribe,
+        transcribe_stream,
+        inference,
+        tokenize,
+        diff,
+        search,
+        merge,
+        summarize,
+        fine_tune_prompt,
+        convert
+    ),
+    components(schemas(
+        TranscribeOptions,
+        LoadPayload,
+        EvictPayload,
+        TokenizePayload,
+        Token,
+        FineTunePromptPayload,
+        PromptCandidate,
+        FineTunedPrompt,
+        GpuDevice,
+        Stats,
+        StatsWindow,
+        Transcript,
+        Segment,
+        AudioMeta,
+        ConfigWarning,
+        ConfigWarningKind,
+        PiiPattern,
+        PiiEntityCount,
+        DiffPayload,
+        WordDiff,
+        Change,
+        ChangeType,
+        TranscribeUrlPayload,
+        SearchPayload,
+        SearchOptions,
+        SearchMatch,
+        SearchSegment,
+        MergePayload,
+        MergedTranscript,
+        SummarizePayload,
+        SummarizeOptions,
+        SummaryStyle,
+        Summary,
+        ConvertPayload
+    ))
 )]
 struct ApiDoc;
 
-pub async fn run(app_handle: tauri::AppHandle, host: String, port: u16) -> eyre::Result<()> {
-    let app = Router::new()
+pub struct ServerOptions {
+    pub host: String,
+    pub port: u16,
+    /// Expose logs in /logs/stream
+    pub expose_logs: bool,
+    pub request_id_header: String,
+    /// Number of model contexts for parallel transcription
+    pub parallel_workers: usize,
+    /// Accepted input formats. others are rejected with 415
+    pub supported_input_formats: Vec<String>,
+    /// Transcription timeout relative to the audio duration
+    pub timeout_realtime_factor: Option<f64>,
+    pub compression_enabled: bool,
+    pub compression_min_size_bytes: usize,
+    /// Log request body fields for debugging clients. api_key and token are redacted
+    pub debug_log_requests: bool,
+    pub access_log_enabled: bool,
+    pub access_log_level: String,
+    /// Serve on this unix domain socket too. ignored on non unix platforms
+    pub unix_socket_path: Option<PathBuf>,
+    /// How often to check if the loaded model file changed on disk
+    pub model_validation_interval_secs: u64,
+    /// warn, reload or error
+    pub model_stale_action: String,
+    /// Cache-Control max-age of status responses
+    pub status_cache_ttl_secs: u64,
+    /// Load models with flash attention unless /load sets it
+    pub flash_attn: bool,
+    /// Load models with DTW timestamps unless /load sets it
+    pub dtw_timestamps: bool,
+    /// Chat completions URL for abstractive summaries. used only with the summarize feature
+    pub summarize_model_url: Option<String>,
+    pub summarize_model: String,
+    /// Warn when free GPU memory is below this
+    pub min_free_vram_gb: f32,
+}
+
+/// What to do when the loaded model file changes on disk
+#[derive(Debug, Clone, Copy)]
+enum ModelStaleAction {
+    Warn,
+    /// Load the model again
+    Reload,
+    /// Fail transcriptions until the model is loaded again
+    Error,
+}
+
+impl std::str::FromStr for ModelStaleAction {
+    type Err = eyre::Report;
+
+    fn from_str(action: &str) -> eyre::Result<Self> {
+        match action {
+            "warn" => Ok(Self::Warn),
+            "reload" => Ok(Self::Reload),
+            "error" => Ok(Self::Error),
+            _ => Err(eyre!("invalid model stale action {}. expected warn, reload or error", action)),
+        }
+    }
+}
+
+/// Check periodically if the loaded model file was modified, eg. overwritten by deployment
+async fn watch_model_file(app_handle: tauri::AppHandle, interval: std::time::Duration, action: ModelStaleAction) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+        let mut model_context = model_context_state.lock().await;
+        let Some(ctx) = model_context.as_mut() else {
+            continue;
+        };
+        if ctx.stale || !ctx.is_modified() {
+            continue;
+        }
+        tracing::warn!("model file {} changed on disk ({:?})", ctx.path, action);
+        let (path, options) = (ctx.path.clone(), ctx.options);
+        match action {
+            ModelStaleAction::Warn => ctx.modified = model_modified(&ctx.path),
+            ModelStaleAction::Error => {
+                ctx.stale = true;
+                drop(model_context);
+                // Worker contexts have the old weights too
+                app_handle.state::<WorkerPool>().clear().log_error();
+            }
+            ModelStaleAction::Reload => {
+                // Load without holding the lock so requests keep using the current context meanwhile
+                drop(model_context);
+                let reload_path = path.clone();
+                let loaded = tauri::async_runtime::spawn_blocking(move || ModelContext::new(&reload_path, options))
+                    .await
+                    .map_err(|e| eyre!("{:?}", e))
+                    .and_then(|result| result);
+                let mut model_context = model_context_state.lock().await;
+                // Another model may be loaded meanwhile
+                let Some(ctx) = model_context
+                    .as_mut()
+                    .filter(|ctx| ctx.path == path && ctx.options == options)
+                else {
+                    continue;
+                };
+                match loaded {
+                    Ok(new_ctx) => {
+                        *ctx = new_ctx;
+                        drop(model_context);
+                        let app_handle = app_handle.clone();
+                        let fill_path = path.clone();
+                        tauri::async_runtime::spawn_blocking(move || app_handle.state::<WorkerPool>().fill(&fill_path, options))
+                            .await
+                            .map_err(|e| eyre!("{:?}", e))
+                            .and_then(|result| result)
+                            .log_error();
+                        tracing::info!("model {} reloaded", path);
+                    }
+                    Err(error) => {
+                        tracing::error!("failed to reload model: {:?}", error);
+                        ctx.stale = true;
+                        drop(model_context);
+                        app_handle.state::<WorkerPool>().clear().log_error();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Settings applied to transcribe requests
+struct RequestSettings {
+    supported_input_formats: Vec<String>,
+    timeout_realtime_factor: Option<f64>,
+    status_cache_ttl_secs: u64,
+    /// Context options of models loaded without explicit options
+    context_defaults: ContextOptions,
+    #[cfg_attr(not(feature = "summarize"), allow(dead_code))]
+    summarize_model_url: Option<String>,
+    #[cfg_attr(not(feature = "summarize"), allow(dead_code))]
+    summarize_model: String,
+    /// Log multipart fields of /inference
+    debug_log_requests: bool,
+}
+
+pub async fn run(app_handle: tauri::AppHandle, options: ServerOptions) -> eyre::Result<()> {
+    let ServerOptions {
+        host,
+        port,
+        expose_logs,
+        request_id_header,
+        parallel_workers,
+        supported_input_formats,
+        timeout_realtime_factor,
+        compression_enabled,
+        compression_min_size_bytes,
+        debug_log_requests,
+        access_log_enabled,
+        access_log_level,
+        unix_socket_path,
+        model_validation_interval_secs,
+        model_stale_action,
+        status_cache_ttl_secs,
+        flash_attn,
+        dtw_timestamps,
+        summarize_model_url,
+        summarize_model,
+        min_free_vram_gb,
+    } = options;
+    tracing::info!("flash attention {}", if flash_attn { "enabled" } else { "disabled" });
+    let context_defaults = ContextOptions {
+        flash_attn,
+        dtw_timestamps,
+        ..Default::default()
+    };
+    let model_stale_action: ModelStaleAction = model_stale_action.parse()?;
+    let request_id_header = HeaderName::try_from(request_id_header).map_err(|e| eyre!("invalid request id header: {:?}", e))?;
+    app_handle.manage(LoadQueue::default());
+    app_handle.manage(TranscribeQueue::default());
+    app_handle.manage(StatsCollector::default());
+    app_handle.manage(std::sync::Mutex::new(DownloadCircuitBreakers::default()));
+    if model_validation_interval_secs > 0 {
+        tauri::async_runtime::spawn(watch_model_file(
+            app_handle.clone(),
+            std::time::Duration::from_secs(model_validation_interval_secs),
+            model_stale_action,
+        ));
+    }
+    app_handle.manage(WorkerPool::new(parallel_workers));
+    app_handle.manage(RequestSettings {
+        supported_input_formats: supported_input_formats.iter().map(|format| format.to_lowercase()).collect(),
+        timeout_realtime_factor,
+        status_cache_ttl_secs,
+        context_defaults,
+        summarize_model_url,
+        summarize_model,
+        debug_log_requests,
+    });
+    let system_stats = Arc::new(std::sync::Mutex::new(SystemStats::default()));
+    app_handle.manage(system_stats.clone());
+    tauri::async_runtime::spawn(SystemStats::refresh_loop(system_stats.clone()));
+    if cfg!(feature = "cuda") {
+        let min_free_vram_bytes = (min_free_vram_gb.max(0.0) as f64 * 1024.0 * 1024.0 * 1024.0) as u64;
+        tauri::async_runtime::spawn(SystemStats::gpu_memory_loop(system_stats, min_free_vram_bytes));
+    }
+    let mut app = Router::new()
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .route("/openapi.json", get(openapi))
+        .route("/health", get(health))
+        .route("/gpu_info", get(gpu_info))
+        .route("/stats", get(stats))
         .route("/transcribe", post(transcribe))
+        .route("/transcribe_stream", post(transcribe_stream))
+        .route(
+            "/inference",
+            post(inference).layer(DefaultBodyLimit::max(INFERENCE_MAX_BODY_BYTES)),
+        )
         .route("/load", post(load))
+        .route("/load_status", get(load_status))
+        .route("/admin/evict_model", post(evict_model))
         .route("/list", get(list_models))
+        .route("/models/download_status", get(download_status))
+        .route("/tokenize", get(tokenize).post(tokenize))
+        .route("/diff", post(diff))
+        .route("/search", post(search))
+        .route("/merge", post(merge))
+        .route("/summarize", post(summarize))
+        .route("/fine_tune_prompt", post(fine_tune_prompt))
+        .route("/convert", post(convert));
+    if expose_logs {
+        app = app.route("/logs/stream", get(logs_stream));
+    }
+    if debug_log_requests {
+        app = app.layer(axum::middleware::from_fn(request_log::log_request_fields));
+    }
+    if compression_enabled {
+        // Event streams are not compressed since the encoder buffers them
+        let predicate = SizeAbove::new(compression_min_size_bytes.min(u16::MAX as usize) as u16)
+            .and(NotForContentType::GRPC)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::const_new("text/event-stream"));
+        app = app.layer(CompressionLayer::new().gzip(true).br(true).compress_when(predicate));
+    }
+    if access_log_enabled {
+        let level = access_log_level
+            .parse::<tracing::Level>()
+            .map_err(|e| eyre!("invalid access log level: {:?}", e))?;
+        let access_log = AccessLog::new(level);
+        app = app.layer(
+            TraceLayer::new_for_http()
+                .make_span_with(access_log)
+                .on_request(())
+                .on_response(access_log),
+        );
+    }
+    // Decompress gzip and brotli request bodies. unsupported encodings are rejected with 415.
+    // Body limits apply to the decompressed body, so compressed uploads can't bypass them
+    let app = app
+        .layer(RequestDecompressionLayer::new())
+        .layer(axum::middleware::from_fn_with_state(
+            request_id_header,
+            request_id::propagate_request_id,
+        ))
         .with_state(app_handle);
 
     let listener = tokio::net::TcpListener::bind(format!("{}:{}", host, port)).await?;
     tracing::info!("Serve on http://{}:{}", host, port);
-    axum::serve(listener, app.into_make_service())
-        .await
-        .map_err(|e| eyre!("{:?}", e))?;
+    let tcp_server = axum::serve(listener, app.clone().into_make_service_with_connect_info::<SocketAddr>()).into_future();
+    match unix_socket_path {
+        #[cfg(unix)]
+        Some(path) => {
+            let _socket_file = SocketFile(path.clone());
+            tokio::select! {
+                result = tcp_server => result.map_err(|e| eyre!("{:?}", e))?,
+                result = serve_unix_socket(app, path) => result?,
+                _ = tokio::signal::ctrl_c() => tracing::info!("Shutting down"),
+            }
+        }
+        #[cfg(not(unix))]
+        Some(path) => {
+            tracing::warn!("unix sockets are not supported on this platform. ignore {}", path.display());
+            tcp_server.await.map_err(|e| eyre!("{:?}", e))?;
+        }
+        None => tcp_server.await.map_err(|e| eyre!("{:?}", e))?,
+    }
     Ok(())
 }
 
+/// Removes the socket file when the server stops
+#[cfg(unix)]
+struct SocketFile(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if self.0.exists() {
+            std::fs::remove_file(&self.0).map_err(|e| eyre!("{:?}", e)).log_error();
+        }
+    }
+}
+
+/// Serve the router on unix domain socket. the socket is accessible to the owner and group
+#[cfg(unix)]
+async fn serve_unix_socket(app: Router, path: PathBuf) -> eyre::Result<()> {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::service::TowerToHyperService;
+    use std::os::unix::fs::PermissionsExt;
+
+    // Stale socket of previous run
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o660))?;
+    tracing::info!("Serve on unix socket {}", path.display());
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+        tauri::async_runtime::spawn(async move {
+            if let Err(error) = Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(socket), service)
+                .await
+            {
+                tracing::debug!("unix socket connection error: {:?}", error);
+            }
+        });
+    }
+}
+
 #[derive(Deserialize, Serialize, ToSchema)]
 struct LoadPayload {
     pub model_path: String,
     pub gpu_device: Option<i32>,
+    /// Load with flash attention (default from --flash-attn)
+    pub flash_attn: Option<bool>,
+    /// Load with DTW timestamps (default from --dtw-timestamps)
+    pub dtw_timestamps: Option<bool>,
+}
+
+/// Serialize model loading. concurrent load requests wait in queue instead of racing on the model context
+struct LoadQueue {
+    semaphore: Semaphore,
+    queued: AtomicUsize,
+    last_error: std::sync::Mutex<Option<String>>,
+}
+
+impl Default for LoadQueue {
+    fn default() -> Self {
+        Self {
+            semaphore: Semaphore::new(1),
+            queued: AtomicUsize::new(0),
+            last_error: std::sync::Mutex::new(None),
+        }
+    }
+}
+
+/// Extra model contexts for transcribing requests in parallel with the main model context.
+/// `size - 1` contexts are created since the main context is used as well
+/// Lock `loaded` before `contexts` when both are needed. see docs/LOCKING_ORDER.md
+struct WorkerPool {
+    size: usize,
+    contexts: std::sync::Mutex<Vec<ModelContext>>,
+    // Model path and context options of the contexts
+    loaded: std::sync::Mutex<Option<(String, ContextOptions)>>,
+}
+
+impl WorkerPool {
+    fn new(size: usize) -> Self {
+        Self {
+            size: size.max(1),
+            contexts: std::sync::Mutex::new(Vec::new()),
+            loaded: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Create the extra contexts for the loaded model
+    fn fill(&self, model_path: &str, options: ContextOptions) -> eyre::Result<()> {
+        if self.size <= 1 {
+            return Ok(());
+        }
+        *self.loaded.lock().map_err(|e| eyre!("{:?}", e))? = Some((model_path.to_string(), options));
+        let mut contexts = self.contexts.lock().map_err(|e| eyre!("{:?}", e))?;
+        // Contexts which are checked out are dropped when they return
+        contexts.retain(|ctx| ctx.path == model_path && ctx.options == options && !ctx.is_modified());
+        while contexts.len() < self.size - 1 {
+            tracing::debug!("create worker context {} for {}", contexts.len() + 1, model_path);
+            contexts.push(ModelContext::new(model_path, options)?);
+        }
+        Ok(())
+    }
+
+    /// Free context unless the request asks for other context options. then the request gets its own context instead
+    fn checkout(&self, options: &TranscribeOptions) -> Option<ModelContext> {
+        let mut contexts = self.contexts.lock().ok()?;
+        if contexts
+            .last()
+            .is_some_and(|ctx| ctx.options.with_overrides(options) != ctx.options)
+        {
+            return None;
+        }
+        contexts.pop()
+    }
+
+    /// Drop the contexts of the model. fails when a context of the model is checked out.
+    /// returns the number of dropped contexts
+    fn evict(&self, model_path: &str) -> eyre::Result<usize> {
+        let mut loaded = self.loaded.lock().map_err(|e| eyre!("{:?}", e))?;
+        if loaded.as_ref().map(|(path, _)| path.as_str()) != Some(model_path) {
+            return Ok(0);
+        }
+        let mut contexts = self.contexts.lock().map_err(|e| eyre!("{:?}", e))?;
+        if contexts.len() < self.size - 1 {
+            eyre::bail!("worker context is transcribing");
+        }
+        *loaded = None;
+        let count = contexts.len();
+        contexts.clear();
+        Ok(count)
+    }
+
+    /// Drop all contexts, including the checked out ones once they return
+    fn clear(&self) -> eyre::Result<()> {
+        let mut loaded = self.loaded.lock().map_err(|e| eyre!("{:?}", e))?;
+        *loaded = None;
+        self.contexts.lock().map_err(|e| eyre!("{:?}", e))?.clear();
+        Ok(())
+    }
+
+    /// Return context to the pool unless another model was loaded meanwhile
+    fn checkin(&self, ctx: ModelContext) {
+        let is_loaded = self
+            .loaded
+            .lock()
+            .is_ok_and(|loaded| loaded.as_ref() == Some(&(ctx.path.clone(), ctx.options)));
+        if !is_loaded {
+            return;
+        }
+        if let Ok(mut contexts) = self.contexts.lock() {
+            if contexts.len() < self.size - 1 {
+                contexts.push(ctx);
+            }
+        }
+    }
+}
+
+const DEFAULT_PRIORITY: u8 = 128;
+
+/// Higher priority first, then earlier arrival
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct QueueTicket {
+    priority: u8,
+    id: Reverse<u64>,
+}
+
+#[derive(Default)]
+struct TranscribeQueueState {
+    waiting: BinaryHeap<QueueTicket>,
+    running: bool,
+    next_id: u64,
+}
+
+/// Transcribe requests waiting for the main model context. requests which get a worker context don't queue
+#[derive(Default)]
+struct TranscribeQueue {
+    state: std::sync::Mutex<TranscribeQueueState>,
+    notify: Notify,
+}
+
+impl TranscribeQueue {
+    fn enqueue(&self, priority: u8) -> QueueSlot<'_> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let ticket = QueueTicket {
+            priority,
+            id: Reverse(state.next_id),
+        };
+        state.next_id += 1;
+        let position = state.waiting.iter().filter(|waiting| **waiting > ticket).count() + state.running as usize;
+        state.waiting.push(ticket);
+        QueueSlot {
+            queue: self,
+            ticket,
+            position,
+            running: false,
+        }
+    }
+}
+
+/// Place in the transcribe queue. leaves the queue or ends the turn when dropped
+struct QueueSlot<'a> {
+    queue: &'a TranscribeQueue,
+    ticket: QueueTicket,
+    /// Requests ahead when queued
+    position: usize,
+    running: bool,
+}
+
+impl QueueSlot<'_> {
+    /// Wait until no request runs and this is the first in queue
+    async fn wait_turn(&mut self) {
+        loop {
+            // Created before the check so a notification in between isn't missed
+            let notified = self.queue.notify.notified();
+            {
+                let mut state = self.queue.state.lock().unwrap_or_else(|e| e.into_inner());
+                if !state.running && state.waiting.peek() == Some(&self.ticket) {
+                    state.waiting.pop();
+                    state.running = true;
+                    self.running = true;
+                    return;
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap_or_else(|e| e.into_inner());
+        if self.running {
+            state.running = false;
+        } else {
+            // Request was cancelled while waiting
+            state.waiting.retain(|ticket| *ticket != self.ticket);
+        }
+        self.queue.notify.notify_waiters();
+    }
 }
 
 /// Load model from path
@@ -50,13 +641,209 @@ struct LoadPayload {
 	post,
 	path = "/load",
 	responses(
-		(status = 200, description = "Load model", body = LoadPayload)
+		(status = 200, description = "Load model", body = LoadPayload),
+		(status = 202, description = "Another model is loading. queued, poll /load_status")
 	),
 )]
-async fn load(State(app_handle): State<tauri::AppHandle>, Json(payload): Json<LoadPayload>) -> Result<String, String> {
-    cmd::load_model(app_handle, payload.model_path, payload.gpu_device)
-        .await
-        .map_err(|e| e.to_string())
+#[tracing::instrument(skip_all, fields(model_path = %payload.model_path))]
+async fn load(
+    State(app_handle): State<tauri::AppHandle>,
+    ValidJson(payload): ValidJson<LoadPayload>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, String)> {
+    let queue = app_handle.state::<LoadQueue>();
+    let context_defaults = app_handle.state::<RequestSettings>().context_defaults;
+    let context_options = ContextOptions {
+        gpu_device: payload.gpu_device,
+        flash_attn: payload.flash_attn.unwrap_or(context_defaults.flash_attn),
+        dtw_timestamps: payload.dtw_timestamps.unwrap_or(context_defaults.dtw_timestamps),
+    };
+    if let Ok(_permit) = queue.semaphore.try_acquire() {
+        let model_path = cmd::load_model_with_options(app_handle.clone(), payload.model_path, context_options)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        app_handle
+            .state::<WorkerPool>()
+            .fill(&model_path, context_options)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        return Ok((StatusCode::OK, Json(json!({"status": "loaded", "model_path": model_path}))));
+    }
+
+    let position = queue.queued.fetch_add(1, Ordering::SeqCst) + 1;
+    tracing::debug!(
+        "model loading in progress. queue {} at position {}",
+        payload.model_path,
+        position
+    );
+    let app_handle = app_handle.clone();
+    let load_task = async move {
+        let queue = app_handle.state::<LoadQueue>();
+        let _permit = queue.semaphore.acquire().await;
+        queue.queued.fetch_sub(1, Ordering::SeqCst);
+        let result = cmd::load_model_with_options(app_handle.clone(), payload.model_path, context_options)
+            .await
+            .and_then(|model_path| app_handle.state::<WorkerPool>().fill(&model_path, context_options));
+        if let Ok(mut last_error) = queue.last_error.lock() {
+            *last_error = result.err().map(|e| e.to_string());
+        }
+    };
+    // Logs of the background load keep the request span
+    tauri::async_runtime::spawn(load_task.instrument(tracing::Span::current()));
+    Ok((StatusCode::ACCEPTED, Json(json!({"status": "queued", "position": position}))))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct EvictPayload {
+    /// Model file name without the `ggml-` prefix and extension (eg. `large-v3`) or the model path
+    pub model_name: String,
+}
+
+fn is_model_name(model_path: &str, model_name: &str) -> bool {
+    let path = std::path::Path::new(model_path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    model_path == model_name || stem == model_name || stem.trim_start_matches("ggml-") == model_name
+}
+
+/// Unload model from memory. `freed_bytes` is estimated from the model file size of every dropped context
+#[utoipa::path(
+	post,
+	path = "/admin/evict_model",
+	responses(
+		(status = 200, description = "Model evicted"),
+		(status = 404, description = "Model isn't loaded"),
+		(status = 409, description = "Model is transcribing")
+	),
+)]
+#[tracing::instrument(skip_all, fields(model_name = %payload.model_name))]
+async fn evict_model(
+    State(app_handle): State<tauri::AppHandle>,
+    ValidJson(payload): ValidJson<EvictPayload>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+    let mut model_context = model_context_state
+        .try_lock()
+        .map_err(|_| (StatusCode::CONFLICT, "model is transcribing".to_string()))?;
+    let model_path = match model_context.as_ref() {
+        Some(ctx) if is_model_name(&ctx.path, &payload.model_name) => ctx.path.clone(),
+        _ => return Err((StatusCode::NOT_FOUND, format!("model {} isn't loaded", payload.model_name))),
+    };
+    let workers = app_handle
+        .state::<WorkerPool>()
+        .evict(&model_path)
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+    // Dropping the context frees the whisper context
+    *model_context = None;
+    let model_size = std::fs::metadata(&model_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    let freed_bytes = model_size * (workers as u64 + 1);
+    tracing::info!("evicted {} and {} worker contexts", model_path, workers);
+    Ok(Json(json!({"evicted": true, "freed_bytes": freed_bytes})))
+}
+
+/// Status of model loading
+#[utoipa::path(
+	get,
+	path = "/load_status",
+	responses(
+		(status = 200, description = "Currently loaded model and queued load requests"),
+		(status = 304, description = "Status didn't change since the If-None-Match ETag")
+	),
+)]
+async fn load_status(State(app_handle): State<tauri::AppHandle>, headers: HeaderMap) -> Response {
+    let queue = app_handle.state::<LoadQueue>();
+    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+    let model_path = model_context_state.lock().await.as_ref().map(|ctx| ctx.path.clone());
+    let last_error = queue.last_error.lock().ok().and_then(|e| e.clone());
+    let status = json!({
+        "loading": queue.semaphore.available_permits() == 0,
+        "queued": queue.queued.load(Ordering::SeqCst),
+        "model_path": model_path,
+        "last_error": last_error,
+    });
+    status_response(&app_handle, &headers, status)
+}
+
+/// Status response with ETag of the body and short max-age. polling clients with unchanged status
+/// get 304 Not Modified without body
+fn status_response(app_handle: &tauri::AppHandle, headers: &HeaderMap, status: Value) -> Response {
+    let body = status.to_string();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    body.hash(&mut hasher);
+    let etag = format!("\"{:x}\"", hasher.finish());
+    let cache_control = format!("max-age={}", app_handle.state::<RequestSettings>().status_cache_ttl_secs);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    let headers = [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+    (headers, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Server health and resource usage
+#[utoipa::path(
+	get,
+	path = "/health",
+	responses(
+		(status = 200, description = "Health and CPU / memory usage")
+	)
+)]
+async fn health(State(app_handle): State<tauri::AppHandle>) -> Json<Value> {
+    let stats = app_handle
+        .state::<Arc<std::sync::Mutex<SystemStats>>>()
+        .lock()
+        .map(|stats| stats.clone())
+        .unwrap_or_default();
+    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+    let model_path = model_context_state.lock().await.as_ref().map(|ctx| ctx.path.clone());
+    let whisper_model_size_bytes = model_path
+        .as_ref()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len());
+    Json(json!({
+        "status": "ok",
+        "model_path": model_path,
+        "cpu_usage_percent": stats.cpu_usage_percent,
+        "memory_used_bytes": stats.memory_used_bytes,
+        "memory_total_bytes": stats.memory_total_bytes,
+        "whisper_model_size_bytes": whisper_model_size_bytes,
+        "gpu_free_vram_gb": stats
+            .gpu_free_vram_bytes
+            .map(|bytes| (bytes as f64 / 1024.0 / 1024.0 / 1024.0 * 10.0).round() / 10.0),
+        "gpu_total_vram_mb": stats.gpu_total_vram_bytes.map(|bytes| bytes / 1024 / 1024),
+        "gpu_memory_warnings_total": stats.gpu_memory_warnings_total,
+    }))
+}
+
+/// GPU devices usable with `gpu_device`
+#[utoipa::path(
+	get,
+	path = "/gpu_info",
+	responses(
+		(status = 200, description = "Available GPU devices")
+	)
+)]
+async fn gpu_info() -> Result<Json<Value>, (StatusCode, String)> {
+    let devices = vibe_core::gpu::gpu_devices().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(json!({ "devices": devices })))
+}
+
+/// Transcription counts, audio seconds and latency of the last hour, day and since start
+#[utoipa::path(
+	get,
+	path = "/stats",
+	responses(
+		(status = 200, description = "Transcription statistics", body = Stats)
+	)
+)]
+async fn stats(State(app_handle): State<tauri::AppHandle>) -> Json<Stats> {
+    Json(app_handle.state::<StatsCollector>().stats())
 }
 
 /// List all Todo items
@@ -90,22 +877,741 @@ async fn list_models(State(app_handle): State<tauri::AppHandle>) -> Result<Json<
     Ok(Json(Value::Array(model_files.into_iter().map(Value::String).collect())))
 }
 
+/// Progress of active model downloads and recently finished ones
+#[utoipa::path(
+	get,
+	path = "/models/download_status",
+	responses(
+		(status = 200, description = "Model downloads status"),
+		(status = 304, description = "Status didn't change since the If-None-Match ETag")
+	)
+)]
+async fn download_status(State(app_handle): State<tauri::AppHandle>, headers: HeaderMap) -> Response {
+    let downloads = app_handle.state::<DownloadRegistry>().list();
+    status_response(&app_handle, &headers, json!({ "downloads": downloads }))
+}
+
+/// Status code by the kind of transcription error
+fn transcribe_error_status(error: &eyre::Report) -> StatusCode {
+    match error.downcast_ref::<TranscribeError>() {
+        Some(TranscribeError::InvalidOptions { .. }) => StatusCode::BAD_REQUEST,
+        Some(TranscribeError::AudioDecodeError(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+        Some(TranscribeError::Timeout) => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Reject files which are not in the supported input formats. the format is detected by the magic bytes
+/// and falls back to the file extension
+fn check_input_format(app_handle: &tauri::AppHandle, path: &str) -> Result<(), (StatusCode, String)> {
+    let path = std::path::Path::new(path);
+    if !path.exists() {
+        return Ok(());
+    }
+    let format = vibe_core::audio::detect_format(path)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(|format| format.to_string())
+        .or_else(|| path.extension().map(|ext| ext.to_string_lossy().to_lowercase()))
+        .unwrap_or_default();
+    let supported = &app_handle.state::<RequestSettings>().supported_input_formats;
+    if !supported.contains(&format) {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("unsupported input format '{}'. supported: {}", format, supported.join(",")),
+        ));
+    }
+    tracing::debug!("input format is {}", format);
+    Ok(())
+}
+
 /// Transcribe file
 #[utoipa::path(
 	post,
 	path = "/transcribe",
 	responses(
-		(status = 200, description = "List all models", body = Transcript)
+		(status = 200, description = "Transcript. with split_channels: {channels: [{channel, result}], result}", body = Transcript),
+		(status = 400, description = "Invalid options or split_channels with mono audio"),
+		(status = 415, description = "Input format is not supported"),
+		(status = 422, description = "Malformed body or invalid options"),
+		(status = 503, description = "audio_url downloads are failing. retry after retry_after_secs")
 	)
 )]
-async fn transcribe(
+async fn transcribe(State(app_handle): State<tauri::AppHandle>, ValidJson(body): ValidJson<Value>) -> Result<Response> {
+    // Transcribe remote file when audio_url is provided. otherwise transcribe local path
+    let Json(transcript) = if body.get("audio_url").is_some() {
+        let payload: TranscribeUrlPayload =
+            serde_json::from_value(body).map_err(|e| validation::ValidationError::new(e.to_string(), None))?;
+        transcribe_url(app_handle, payload).await?
+    } else {
+        let payload: TranscribeOptions = validation::from_value(body, None)?;
+        validation::check_conflicts(&payload, None)?;
+        transcribe_options(app_handle, payload).await?
+    };
+    Ok(transcript_response(transcript))
+}
+
+/// Uploaded audio files of /inference
+const INFERENCE_MAX_BODY_BYTES: usize = 1024 * 1024 * 1024;
+
+/// whisper.cpp server compatible transcribe. multipart form with `file` and optional `temperature`, `language`,
+/// `translate`, `word_timestamps`, `prompt` and `response_format` (json, verbose_json, text, srt or vtt)
+#[utoipa::path(
+	post,
+	path = "/inference",
+	responses(
+		(status = 200, description = "Transcript in the whisper.cpp response format"),
+		(status = 400, description = "Invalid form or response_format")
+	)
+)]
+async fn inference(State(app_handle): State<tauri::AppHandle>, mut multipart: Multipart) -> Result<Response> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let mut path = None;
+    let mut options = TranscribeOptions::default();
+    let mut response_format = "json".to_string();
+    let debug_log_requests = app_handle.state::<RequestSettings>().debug_log_requests;
+    let result: Result<Response> = async {
+        while let Some(field) = multipart.next_field().await.map_err(|e| bad_request(e.body_text()))? {
+            let name = field.name().unwrap_or_default().to_string();
+            let content_type = field.content_type().map(str::to_string);
+            if name == "file" {
+                let extension = field
+                    .file_name()
+                    .and_then(|name| std::path::Path::new(name).extension())
+                    .map(|ext| ext.to_string_lossy().to_string())
+                    .unwrap_or("tmp".into());
+                let data = field.bytes().await.map_err(|e| bad_request(e.body_text()))?;
+                if debug_log_requests {
+                    request_log::log_multipart_field(&name, content_type.as_deref(), data.len(), None);
+                }
+                let file_path = std::env::temp_dir().join(format!("{}.{}", random_string(10), extension));
+                std::fs::write(&file_path, data).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                path = Some(file_path);
+                continue;
+            }
+            let value = field.text().await.map_err(|e| bad_request(e.body_text()))?;
+            if debug_log_requests {
+                request_log::log_multipart_field(&name, content_type.as_deref(), value.len(), Some(value.as_str()));
+            }
+            let value = value.trim();
+            match name.as_str() {
+                "temperature" => {
+                    let temperature = value
+                        .parse()
+                        .map_err(|_| bad_request(format!("invalid temperature {}", value)))?;
+                    options.temperature = Some(temperature);
+                }
+                "language" if value != "auto" && !value.is_empty() => options.lang = Some(value.to_string()),
+                "translate" => options.translate = Some(value == "true"),
+                "word_timestamps" => options.word_timestamps = Some(value == "true"),
+                "prompt" if !value.is_empty() => options.init_prompt = Some(value.to_string()),
+                "response_format" => response_format = value.to_string(),
+                _ => tracing::debug!("ignore inference field {}", name),
+            }
+        }
+        let file_path = path.as_ref().ok_or_else(|| bad_request("file is required".into()))?;
+        if !["json", "verbose_json", "text", "srt", "vtt"].contains(&response_format.as_str()) {
+            return Err(bad_request(format!("unsupported response_format {}", response_format)).into());
+        }
+        options.path = file_path.to_string_lossy().to_string();
+        options
+            .validate()
+            .map_err(|e| validation::ValidationError::from_errors(&e, None))?;
+        validation::check_conflicts(&options, None)?;
+        let Json(transcript) = transcribe_options(app_handle, options.clone()).await?;
+        let response = match response_format.as_str() {
+            "text" => transcript.as_text().into_response(),
+            "srt" => transcript.as_srt().into_response(),
+            "vtt" => transcript.as_vtt(false, None).into_response(),
+            format => Json(vibe_core::formats::whisper_cpp_json(
+                &transcript,
+                &options,
+                format == "verbose_json",
+            ))
+            .into_response(),
+        };
+        Ok(response)
+    }
+    .await;
+    if let Some(path) = path.filter(|path| path.exists()) {
+        std::fs::remove_file(&path).map_err(|e| eyre!("{:?}", e)).log_error();
+    }
+    result
+}
+
+#[derive(Deserialize)]
+struct TranscribeStreamQuery {
+    /// `wav` (default) or mono little endian `pcm16_<sample rate>`, eg. `pcm16_16000`
+    streaming_input_format: Option<String>,
+    lang: Option<String>,
+}
+
+/// Sample rate of the `pcm16_<sample rate>` stream format
+fn pcm16_sample_rate(format: &str) -> Option<u32> {
+    format.strip_prefix("pcm16_")?.parse().ok().filter(|rate| *rate > 0)
+}
+
+/// Wrap raw pcm16 samples in a wav file
+fn pcm16_to_wav(input: &std::path::Path, output: &std::path::Path, sample_rate: u32) -> eyre::Result<()> {
+    let data = std::fs::read(input)?;
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(output, spec)?;
+    for sample in data.chunks_exact(2) {
+        writer.write_sample(i16::from_le_bytes([sample[0], sample[1]]))?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Transcribe raw audio streamed as the request body (eg. with `Transfer-Encoding: chunked`) when the size isn't
+/// known in advance. the stream is buffered to a temp file and transcribed when it closes
+#[utoipa::path(
+	post,
+	path = "/transcribe_stream",
+	params(
+		("streaming_input_format" = Option<String>, Query, description = "wav (default) or pcm16_<sample rate>, eg. pcm16_16000"),
+		("lang" = Option<String>, Query, description = "Language of the audio")
+	),
+	responses(
+		(status = 200, description = "Transcript. the received size is in the x-bytes-received header", body = Transcript),
+		(status = 400, description = "Unsupported streaming_input_format or broken stream"),
+		(status = 413, description = "Stream is larger than 1 GiB")
+	)
+)]
+async fn transcribe_stream(
     State(app_handle): State<tauri::AppHandle>,
-    Json(payload): Json<TranscribeOptions>,
+    Query(query): Query<TranscribeStreamQuery>,
+    body: axum::body::Body,
+) -> Result<Response> {
+    let format = query.streaming_input_format.as_deref().unwrap_or("wav");
+    let sample_rate = match format {
+        "wav" => None,
+        format => Some(pcm16_sample_rate(format).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "unsupported streaming_input_format {}. use wav or pcm16_<sample rate>",
+                    format
+                ),
+            )
+        })?),
+    };
+    let extension = if sample_rate.is_some() { "pcm" } else { "wav" };
+    let stream_path = std::env::temp_dir().join(format!("{}.{}", random_string(10), extension));
+    let mut wav_path = None;
+    let result: Result<Response> = async {
+        let internal_error = |e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        let mut file = std::fs::File::create(&stream_path).map_err(internal_error)?;
+        let mut stream = body.into_data_stream();
+        let mut bytes_received = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+            bytes_received += chunk.len();
+            if bytes_received > INFERENCE_MAX_BODY_BYTES {
+                return Err((StatusCode::PAYLOAD_TOO_LARGE, "stream is too large".to_string()).into());
+            }
+            file.write_all(&chunk).map_err(internal_error)?;
+        }
+        drop(file);
+        tracing::debug!("received {} bytes of {} stream", bytes_received, format);
+        let path = match sample_rate {
+            Some(sample_rate) => {
+                let path = stream_path.with_extension("wav");
+                wav_path = Some(path.clone());
+                pcm16_to_wav(&stream_path, &path, sample_rate).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                path
+            }
+            None => stream_path.clone(),
+        };
+        let options = TranscribeOptions {
+            path: path.to_string_lossy().to_string(),
+            lang: query.lang.clone(),
+            ..Default::default()
+        };
+        let Json(transcript) = transcribe_options(app_handle, options).await?;
+        let mut response = transcript_response(transcript);
+        response.headers_mut().insert("x-bytes-received", bytes_received.into());
+        Ok(response)
+    }
+    .await;
+    for path in [Some(stream_path), wav_path].into_iter().flatten() {
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| eyre!("{:?}", e)).log_error();
+        }
+    }
+    result
+}
+
+/// Split channels transcript is returned as `{"channels": [...], "result": merged}`
+fn transcript_response(mut transcript: Transcript) -> Response {
+    match transcript.channels.take() {
+        Some(channels) => Json(json!({ "channels": channels, "result": transcript })).into_response(),
+        None => Json(transcript).into_response(),
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct TranscribeUrlPayload {
+    pub audio_url: String,
+    /// Transcribe options without path
+    #[serde(default)]
+    pub task_options: Option<Value>,
+    /// Model path to load before transcribing
+    pub model: Option<String>,
+}
+
+/// Download audio from url to temp file
+async fn download_audio(url: &str) -> eyre::Result<std::path::PathBuf> {
+    let extension = url::Url::parse(url)?
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| std::path::Path::new(name).extension())
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or("tmp".into());
+    let path = std::env::temp_dir().join(format!("{}.{}", random_string(10), extension));
+    let mut downloader = vibe_core::downloader::Downloader::new();
+    downloader.download(url, path.clone(), |_, _| false).await?;
+    Ok(path)
+}
+
+#[tracing::instrument(skip_all, fields(audio_url = %payload.audio_url))]
+async fn transcribe_url(app_handle: tauri::AppHandle, payload: TranscribeUrlPayload) -> Result<Json<Transcript>> {
+    let host = url::Url::parse(&payload.audio_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .ok_or_else(|| validation::ValidationError::new("audio_url must be URL with host", Some("audio_url")))?;
+    let circuit_breakers = app_handle.state::<std::sync::Mutex<DownloadCircuitBreakers>>();
+    let permit = match DownloadPermit::acquire(&circuit_breakers, &host) {
+        Ok(permit) => permit,
+        Err(retry_after) => {
+            let retry_after_secs = retry_after.as_secs_f64().ceil() as u64;
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                Json(json!({"error": "download_unavailable", "retry_after_secs": retry_after_secs})),
+            )
+                .into());
+        }
+    };
+    let download = download_audio(&payload.audio_url).await;
+    permit.finish(download.is_ok());
+    let path = download.map_err(|e| (StatusCode::BAD_REQUEST, format!("failed to download audio: {:?}", e)))?;
+    let mut task_options = payload.task_options.unwrap_or(json!({}));
+    let result: Result<Json<Transcript>> = async {
+        let options = task_options
+            .as_object_mut()
+            .ok_or_else(|| validation::ValidationError::new("task_options must be object", Some("task_options")))?;
+        options.insert("path".into(), json!(path.to_string_lossy()));
+        let options: TranscribeOptions = validation::from_value(task_options, Some("task_options"))?;
+        validation::check_conflicts(&options, Some("task_options"))?;
+        if let Some(model) = payload.model {
+            let context_options = app_handle
+                .state::<RequestSettings>()
+                .context_defaults
+                .with_overrides(&options);
+            cmd::load_model_with_options(app_handle.clone(), model, context_options)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        }
+        Ok(transcribe_options(app_handle, options).await?)
+    }
+    .await;
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| eyre!("{:?}", e)).log_error();
+    }
+    result
+}
+
+/// Transcribe and record the result in the server stats
+#[tracing::instrument(skip_all, fields(path = %payload.path))]
+async fn transcribe_options(
+    app_handle: tauri::AppHandle,
+    payload: TranscribeOptions,
 ) -> Result<Json<Transcript>, (StatusCode, String)> {
-    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
-    let transcript = cmd::transcribe(app_handle.clone(), payload, model_context_state, DiarizeOptions::default())
+    let start = std::time::Instant::now();
+    let path = std::path::PathBuf::from(&payload.path);
+    let result = run_transcription(app_handle.clone(), payload).await;
+    let audio_secs = match result {
+        Ok(_) => vibe_core::audio::get_audio_duration(&path).unwrap_or_default(),
+        Err(_) => 0.0,
+    };
+    app_handle
+        .state::<StatsCollector>()
+        .record(audio_secs, start.elapsed(), result.is_ok());
+    result
+}
+
+async fn run_transcription(
+    app_handle: tauri::AppHandle,
+    mut payload: TranscribeOptions,
+) -> Result<Json<Transcript>, (StatusCode, String)> {
+    check_input_format(&app_handle, &payload.path)?;
+    if let Some(factor) = app_handle.state::<RequestSettings>().timeout_realtime_factor {
+        if payload.timeout_secs.is_none() && std::path::Path::new(&payload.path).exists() {
+            let duration = vibe_core::audio::get_audio_duration(std::path::Path::new(&payload.path))
+                .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+            tracing::debug!("audio duration is {:.2}s", duration);
+            // Unknown duration (eg. streamed WAV without size) runs without timeout
+            let timeout_secs = (duration * factor) as f32;
+            payload.timeout_secs = (timeout_secs > 0.0).then_some(timeout_secs);
+        }
+    }
+
+    let memory_tracker = MemoryTracker::start();
+    // Use a free worker context if there's one. otherwise wait for the main context
+    if let Some(ctx) = app_handle.state::<WorkerPool>().checkout(&payload) {
+        // Blocking task doesn't inherit the span. enter it so logs keep the request id
+        let span = tracing::Span::current();
+        let (ctx, result) = tauri::async_runtime::spawn_blocking(move || {
+            let _entered = span.entered();
+            let result = vibe_core::transcribe::transcribe(&ctx.handle, &payload, None, None, None, None);
+            (ctx, result)
+        })
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        app_handle.state::<WorkerPool>().checkin(ctx);
+        let mut transcript = result.map_err(|e| {
+            let e = eyre::Report::new(e);
+            (transcribe_error_status(&e), format!("{:?}", e))
+        })?;
+        transcript.memory_used_bytes = memory_tracker.stop();
+        return Ok(Json(transcript));
+    }
+
+    let queue = app_handle.state::<TranscribeQueue>();
+    let mut slot = queue.enqueue(payload.priority.unwrap_or(DEFAULT_PRIORITY));
+    let estimated_wait_secs = app_handle.state::<StatsCollector>().avg_latency().as_secs_f32() * slot.position as f32;
+    tracing::debug!(
+        "queued at position {} with priority {}. estimated wait {:.1}s",
+        slot.position,
+        slot.ticket.priority,
+        estimated_wait_secs
+    );
+    slot.wait_turn().await;
+
+    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+    let mut transcript = cmd::transcribe(app_handle.clone(), payload, model_context_state, DiarizeOptions::default())
+        .await
+        .map_err(|e| (transcribe_error_status(&e), format!("{:?}", e)))?;
+    transcript.memory_used_bytes = memory_tracker.stop();
+    transcript.queue_position = Some(slot.position);
+    transcript.estimated_wait_secs = Some(estimated_wait_secs);
 
     Ok(Json(transcript))
 }
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct TokenizePayload {
+    pub text: String,
+    /// Model path to load before tokenizing. defaults to the loaded model
+    pub model: Option<String>,
+}
+
+/// Tokens of the text in the model vocabulary. the body is accepted with GET too
+#[utoipa::path(
+	post,
+	path = "/tokenize",
+	responses(
+		(status = 200, description = "Tokens and token count"),
+		(status = 409, description = "No model is loaded")
+	)
+)]
+async fn tokenize(
+    State(app_handle): State<tauri::AppHandle>,
+    ValidJson(payload): ValidJson<TokenizePayload>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    if let Some(model) = payload.model {
+        let context_options = app_handle.state::<RequestSettings>().context_defaults;
+        cmd::load_model_with_options(app_handle.clone(), model, context_options)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    let model_context_state: tauri::State<'_, Mutex<Option<ModelContext>>> = app_handle.state();
+    let model_context = model_context_state.lock().await;
+    let ctx = model_context
+        .as_ref()
+        .ok_or((StatusCode::CONFLICT, "model isn't loaded".to_string()))?;
+    let tokens: Vec<Token> = vibe_core::transcribe::tokenize(&ctx.handle, &payload.text).map_err(|e| {
+        let e = eyre::Report::new(e);
+        (transcribe_error_status(&e), format!("{:?}", e))
+    })?;
+    Ok(Json(json!({ "tokens": tokens, "token_count": tokens.len() })))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct DiffPayload {
+    pub text_a: String,
+    pub text_b: String,
+}
+
+/// Word level diff between two transcripts
+#[utoipa::path(
+	post,
+	path = "/diff",
+	responses(
+		(status = 200, description = "Word level diff from text_a to text_b", body = WordDiff)
+	)
+)]
+async fn diff(ValidJson(payload): ValidJson<DiffPayload>) -> Json<WordDiff> {
+    Json(vibe_core::diff::diff_words(&payload.text_a, &payload.text_b))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct SearchPayload {
+    pub segments: Vec<Segment>,
+    #[serde(flatten)]
+    pub options: SearchOptions,
+}
+
+/// Search text in transcript segments
+#[utoipa::path(
+	post,
+	path = "/search",
+	responses(
+		(status = 200, description = "Segments which contain the query")
+	)
+)]
+async fn search(ValidJson(payload): ValidJson<SearchPayload>) -> Json<Value> {
+    let matches = vibe_core::search::search_segments(&payload.segments, &payload.options);
+    Json(json!({ "matches": matches }))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct MergePayload {
+    pub transcripts: Vec<Transcript>,
+    /// Start time in seconds of every transcript part
+    #[serde(default)]
+    pub time_offsets: Vec<f64>,
+}
+
+/// Merge transcripts of parts into one sorted by time
+#[utoipa::path(
+	post,
+	path = "/merge",
+	responses(
+		(status = 200, description = "Merged transcript and overlap warnings", body = MergedTranscript)
+	)
+)]
+async fn merge(ValidJson(payload): ValidJson<MergePayload>) -> Result<Json<MergedTranscript>, (StatusCode, String)> {
+    let merged = vibe_core::merge::merge_transcripts(payload.transcripts, &payload.time_offsets)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(Json(merged))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct SummarizePayload {
+    pub segments: Vec<Segment>,
+    #[serde(flatten)]
+    pub options: SummarizeOptions,
+}
+
+/// Summarize transcript segments. abstractive with --summarize-model-url, otherwise extractive
+#[utoipa::path(
+	post,
+	path = "/summarize",
+	responses(
+		(status = 200, description = "Summary and key topics", body = Summary),
+		(status = 502, description = "Summarize model request failed")
+	)
+)]
+#[cfg_attr(not(feature = "summarize"), allow(unused_variables))]
+async fn summarize(
+    State(app_handle): State<tauri::AppHandle>,
+    ValidJson(payload): ValidJson<SummarizePayload>,
+) -> Result<Json<Summary>, (StatusCode, String)> {
+    #[cfg(feature = "summarize")]
+    {
+        let settings = app_handle.state::<RequestSettings>();
+        if let Some(url) = settings.summarize_model_url.as_deref() {
+            let summary =
+                vibe_core::summarize::summarize_with_model(url, &settings.summarize_model, &payload.segments, &payload.options)
+                    .await
+                    .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+            return Ok(Json(summary));
+        }
+    }
+    Ok(Json(vibe_core::summarize::summarize_segments(
+        &payload.segments,
+        &payload.options,
+    )))
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct FineTunePromptPayload {
+    /// Audio sample path
+    pub path: String,
+    /// medical, legal, technical or general (default)
+    pub domain: Option<String>,
+    pub lang: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct PromptCandidate {
+    pub prompt: String,
+    /// Transcript confidence of the sample with this prompt
+    pub confidence: f32,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct FineTunedPrompt {
+    pub best_prompt: String,
+    pub confidence: f32,
+    /// Other candidates sorted by confidence
+    pub alternatives: Vec<PromptCandidate>,
+}
+
+/// Transcribe the sample with every prompt of the domain library and suggest the one with the highest confidence.
+/// Requires the prompt-tuning feature
+#[utoipa::path(
+	post,
+	path = "/fine_tune_prompt",
+	responses(
+		(status = 200, description = "Best prompt and the alternatives", body = FineTunedPrompt),
+		(status = 400, description = "Unknown domain"),
+		(status = 501, description = "Compiled without the prompt-tuning feature")
+	)
+)]
+#[cfg_attr(not(feature = "prompt-tuning"), allow(unused_variables))]
+async fn fine_tune_prompt(
+    State(app_handle): State<tauri::AppHandle>,
+    ValidJson(payload): ValidJson<FineTunePromptPayload>,
+) -> Result<Json<FineTunedPrompt>, (StatusCode, String)> {
+    #[cfg(not(feature = "prompt-tuning"))]
+    return Err((
+        StatusCode::NOT_IMPLEMENTED,
+        "compiled without the prompt-tuning feature".to_string(),
+    ));
+    #[cfg(feature = "prompt-tuning")]
+    {
+        let domain = payload.domain.as_deref().unwrap_or("general");
+        let prompts = crate::prompt_library::candidate_prompts(domain).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let mut candidates = Vec::new();
+        for prompt in prompts {
+            let options = TranscribeOptions {
+                path: payload.path.clone(),
+                lang: payload.lang.clone(),
+                init_prompt: Some(prompt.clone()).filter(|prompt| !prompt.is_empty()),
+                ..Default::default()
+            };
+            let Json(transcript) = transcribe_options(app_handle.clone(), options).await?;
+            let confidence = transcript.transcript_confidence.unwrap_or_default();
+            tracing::debug!("prompt {:?} confidence {:.3}", prompt, confidence);
+            candidates.push(PromptCandidate { prompt, confidence });
+        }
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let best = candidates.remove(0);
+        Ok(Json(FineTunedPrompt {
+            best_prompt: best.prompt,
+            confidence: best.confidence,
+            alternatives: candidates,
+        }))
+    }
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+struct ConvertPayload {
+    /// Transcript file content
+    pub content: String,
+    /// srt, vtt or json
+    pub from_format: String,
+    /// srt, vtt, txt, json, ass, audacity or docx (when compiled with the docx feature)
+    pub to_format: String,
+    /// Title of docx output
+    pub title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConvertQuery {
+    /// Comma separated formats returned as multipart/mixed parts instead of to_format
+    formats: Option<String>,
+}
+
+/// Convert transcript between formats without transcribing.
+/// With `Accept: multipart/mixed` and `?formats=json,srt` every format is returned as a part of one response
+#[utoipa::path(
+	post,
+	path = "/convert",
+	params(
+		("formats" = Option<String>, Query, description = "Comma separated formats for multipart/mixed response")
+	),
+	responses(
+		(status = 200, description = "Converted transcript with the content type of the target format"),
+		(status = 400, description = "Invalid input or unsupported format")
+	)
+)]
+async fn convert(
+    headers: HeaderMap,
+    Query(query): Query<ConvertQuery>,
+    ValidJson(payload): ValidJson<ConvertPayload>,
+) -> Result<Response, (StatusCode, String)> {
+    let accepts_multipart = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("multipart/mixed"));
+    if let (true, Some(formats)) = (accepts_multipart, query.formats) {
+        let formats: Vec<&str> = formats.split(',').map(str::trim).filter(|f| !f.is_empty()).collect();
+        let boundary = random_string(24);
+        let output = vibe_core::formats::convert_multipart(&payload.content, &payload.from_format, &formats, &boundary)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:?}", e)))?;
+        let content_type = format!("multipart/mixed; boundary={}", boundary);
+        return Ok(([(header::CONTENT_TYPE, content_type)], output).into_response());
+    }
+    #[cfg(feature = "docx")]
+    if payload.to_format == "docx" {
+        let transcript = vibe_core::formats::parse(&payload.content, &payload.from_format)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:?}", e)))?;
+        let title = payload.title.as_deref().unwrap_or("Transcript");
+        let output = vibe_core::formats::docx::transcript_to_docx(&transcript, title)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{:?}", e)))?;
+        let headers = [
+            (header::CONTENT_TYPE, vibe_core::formats::content_type("docx")),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"transcript.docx\""),
+        ];
+        return Ok((headers, output).into_response());
+    }
+    let output = vibe_core::formats::convert(&payload.content, &payload.from_format, &payload.to_format)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{:?}", e)))?;
+    let content_type = vibe_core::formats::content_type(&payload.to_format);
+    Ok(([(header::CONTENT_TYPE, content_type)], output).into_response())
+}
+
+#[derive(Deserialize)]
+struct LogsStreamQuery {
+    /// Minimum level (default: trace)
+    level: Option<String>,
+}
+
+/// Stream log lines as server sent events
+async fn logs_stream(
+    Query(query): Query<LogsStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let min_level = match query.level {
+        Some(level) => level
+            .parse::<tracing::Level>()
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => tracing::Level::TRACE,
+    };
+    let receiver = logging::LOG_STREAM.subscribe();
+    let stream = futures::stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(line) => {
+                    let level = serde_json::from_str::<Value>(&line)
+                        .ok()
+                        .and_then(|v| v["level"].as_str().and_then(|l| l.parse::<tracing::Level>().ok()));
+                    // More verbose levels are greater
+                    if level.map(|level| level <= min_level).unwrap_or(true) {
+                        return Some((Ok(Event::default().data(line)), receiver));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
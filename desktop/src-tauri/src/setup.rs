@@ -4,22 +4,242 @@ use crate::{
     panic_hook,
     utils::{get_issue_url, LogError},
 };
+use chrono::Local;
 use eyre::eyre;
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tauri::{App, Manager};
 use tauri_plugin_dialog::DialogExt;
 use tauri_plugin_shell::ShellExt;
 use tauri_plugin_store::StoreBuilder;
 use tokio::sync::Mutex;
-use vibe_core::transcribe::WhisperContext;
+use vibe_core::transcribe::{ContextOptions, WhisperContext};
 
 pub static STATIC_APP: Lazy<std::sync::Mutex<Option<tauri::AppHandle>>> = Lazy::new(|| std::sync::Mutex::new(None));
 
 pub struct ModelContext {
     pub path: String,
-    pub gpu_device: Option<i32>,
+    pub options: ContextOptions,
     pub handle: WhisperContext,
+    /// Modification time of the model file when it was loaded
+    pub modified: Option<SystemTime>,
+    /// Model file changed on disk after loading. transcribe fails until the model is loaded again
+    pub stale: bool,
+}
+
+impl ModelContext {
+    pub fn new(path: &str, options: ContextOptions) -> eyre::Result<Self> {
+        let modified = model_modified(path);
+        Ok(Self {
+            path: path.to_string(),
+            options,
+            handle: vibe_core::transcribe::create_context(std::path::Path::new(path), options)?,
+            modified,
+            stale: false,
+        })
+    }
+
+    /// Model file was modified since it was loaded
+    pub fn is_modified(&self) -> bool {
+        model_modified(&self.path) != self.modified
+    }
+}
+
+pub fn model_modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// How often system stats are refreshed
+const SYSTEM_STATS_INTERVAL: Duration = Duration::from_secs(1);
+/// How often GPU memory is checked. querying the GPU is slower than the system stats
+const GPU_MEMORY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// CPU and memory usage of the machine
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SystemStats {
+    pub cpu_usage_percent: f32,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Free memory of all GPUs. None without GPU memory info
+    pub gpu_free_vram_bytes: Option<u64>,
+    pub gpu_total_vram_bytes: Option<u64>,
+    /// Times free GPU memory was below the minimum
+    pub gpu_memory_warnings_total: u64,
+}
+
+impl SystemStats {
+    /// Refresh the stats in background at most once per interval
+    pub async fn refresh_loop(stats: Arc<std::sync::Mutex<SystemStats>>) {
+        let mut system = sysinfo::System::new();
+        loop {
+            system.refresh_cpu_usage();
+            system.refresh_memory();
+            if let Ok(mut stats) = stats.lock() {
+                stats.cpu_usage_percent = system.global_cpu_usage();
+                stats.memory_used_bytes = system.used_memory();
+                stats.memory_total_bytes = system.total_memory();
+            }
+            tokio::time::sleep(SYSTEM_STATS_INTERVAL.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL)).await;
+        }
+    }
+
+    /// Refresh GPU memory in background and warn when free memory is below `min_free_bytes`
+    pub async fn gpu_memory_loop(stats: Arc<std::sync::Mutex<SystemStats>>, min_free_bytes: u64) {
+        loop {
+            let devices = tauri::async_runtime::spawn_blocking(vibe_core::gpu::gpu_devices)
+                .await
+                .map_err(|e| eyre!("{:?}", e))
+                .and_then(|devices| devices)
+                .unwrap_or_else(|e| {
+                    tracing::debug!("failed to query GPU memory: {:?}", e);
+                    Vec::new()
+                });
+            let sum = |memory: fn(&vibe_core::gpu::GpuDevice) -> Option<u64>| {
+                devices
+                    .iter()
+                    .map(memory)
+                    .sum::<Option<u64>>()
+                    .filter(|_| !devices.is_empty())
+            };
+            let free = sum(|device| device.memory_free_bytes);
+            let total = sum(|device| device.memory_total_bytes);
+            if let Ok(mut stats) = stats.lock() {
+                stats.gpu_free_vram_bytes = free;
+                stats.gpu_total_vram_bytes = total;
+                if free.is_some_and(|free| free < min_free_bytes) {
+                    stats.gpu_memory_warnings_total += 1;
+                    tracing::warn!(
+                        "free GPU memory is {} MB. below the minimum of {} MB",
+                        free.unwrap_or_default() / 1024 / 1024,
+                        min_free_bytes / 1024 / 1024
+                    );
+                }
+            }
+            tokio::time::sleep(GPU_MEMORY_INTERVAL).await;
+        }
+    }
+}
+
+/// Window for computing download speed
+const DOWNLOAD_SPEED_WINDOW: Duration = Duration::from_secs(5);
+/// How long finished downloads are kept in the registry
+const FINISHED_DOWNLOAD_TTL: Duration = Duration::from_secs(60);
+const MAX_FINISHED_DOWNLOADS: usize = 5;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DownloadStatus {
+    pub model_name: String,
+    pub download_id: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+    pub percent: f64,
+    pub started_at: String,
+    pub speed_bps: u64,
+    pub status: String,
+}
+
+struct ActiveDownload {
+    status: DownloadStatus,
+    // (time, downloaded bytes) samples in the last speed window
+    samples: VecDeque<(Instant, u64)>,
+}
+
+/// Track progress of model downloads
+#[derive(Default)]
+pub struct DownloadRegistry {
+    next_id: AtomicU64,
+    active: std::sync::Mutex<HashMap<String, ActiveDownload>>,
+    finished: std::sync::Mutex<VecDeque<(Instant, DownloadStatus)>>,
+}
+
+impl DownloadRegistry {
+    /// Register new download and return its id
+    pub fn start(&self, model_name: String) -> String {
+        let download_id = format!(
+            "{}-{}",
+            Local::now().format("%Y%m%d%H%M%S"),
+            self.next_id.fetch_add(1, Ordering::SeqCst)
+        );
+        let status = DownloadStatus {
+            model_name,
+            download_id: download_id.clone(),
+            downloaded_bytes: 0,
+            total_bytes: 0,
+            percent: 0.0,
+            started_at: Local::now().to_rfc3339(),
+            speed_bps: 0,
+            status: "downloading".into(),
+        };
+        let download = ActiveDownload {
+            status,
+            samples: VecDeque::new(),
+        };
+        self.active.lock().unwrap().insert(download_id.clone(), download);
+        download_id
+    }
+
+    pub fn update(&self, download_id: &str, downloaded_bytes: u64, total_bytes: u64) {
+        let mut active = self.active.lock().unwrap();
+        let Some(download) = active.get_mut(download_id) else {
+            return;
+        };
+        let now = Instant::now();
+        download.samples.push_back((now, downloaded_bytes));
+        while download
+            .samples
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > DOWNLOAD_SPEED_WINDOW)
+        {
+            download.samples.pop_front();
+        }
+        if let Some((first_time, first_bytes)) = download.samples.front() {
+            let elapsed = now.duration_since(*first_time).as_secs_f64();
+            if elapsed > 0.0 {
+                download.status.speed_bps = (downloaded_bytes.saturating_sub(*first_bytes) as f64 / elapsed) as u64;
+            }
+        }
+        download.status.downloaded_bytes = downloaded_bytes;
+        download.status.total_bytes = total_bytes;
+        if total_bytes > 0 {
+            download.status.percent = downloaded_bytes as f64 / total_bytes as f64;
+        }
+    }
+
+    /// Move download to finished list with the given status. eg. completed / failed
+    pub fn finish(&self, download_id: &str, status: &str) {
+        let Some(download) = self.active.lock().unwrap().remove(download_id) else {
+            return;
+        };
+        let mut download_status = download.status;
+        download_status.status = status.into();
+        download_status.speed_bps = 0;
+        let mut finished = self.finished.lock().unwrap();
+        finished.push_back((Instant::now(), download_status));
+        while finished.len() > MAX_FINISHED_DOWNLOADS {
+            finished.pop_front();
+        }
+    }
+
+    /// Active downloads followed by recently finished ones
+    pub fn list(&self) -> Vec<DownloadStatus> {
+        let mut downloads: Vec<DownloadStatus> = self
+            .active
+            .lock()
+            .unwrap()
+            .values()
+            .map(|download| download.status.clone())
+            .collect();
+        downloads.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        let mut finished = self.finished.lock().unwrap();
+        finished.retain(|(time, _)| time.elapsed() < FINISHED_DOWNLOAD_TTL);
+        downloads.extend(finished.iter().map(|(_, status)| status.clone()));
+        downloads
+    }
 }
 
 pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
@@ -32,6 +252,12 @@ pub fn setup(app: &App) -> Result<(), Box<dyn std::error::Error>> {
     // Manage model context
     app.manage(Mutex::new(None::<ModelContext>));
 
+    // Manage model downloads status
+    app.manage(DownloadRegistry::default());
+
+    #[cfg(feature = "microphone")]
+    app.manage(crate::cmd::microphone::RecordingState::default());
+
     let mut store = StoreBuilder::new(STORE_FILENAME).build(app.handle().clone());
     let _ = store.load();
 
@@ -0,0 +1,149 @@
+use crate::utils::random_string;
+use eyre::{bail, Result};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+fn model_files(models_folder: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(models_folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn check_models(models_folder: &Path, model: Option<&Path>) -> CheckResult {
+    if let Some(model) = model {
+        if !model.exists() {
+            return CheckResult::new("models", CheckStatus::Warning, format!("model {} not found", model.display()));
+        }
+    }
+    let count = model_files(models_folder).len();
+    if count == 0 {
+        return CheckResult::new(
+            "models",
+            CheckStatus::Warning,
+            format!("no models in {}", models_folder.display()),
+        );
+    }
+    CheckResult::new(
+        "models",
+        CheckStatus::Ok,
+        format!("{} models in {}", count, models_folder.display()),
+    )
+}
+
+fn check_ffmpeg() -> CheckResult {
+    match vibe_core::audio::find_ffmpeg_path() {
+        Some(path) => CheckResult::new("ffmpeg", CheckStatus::Ok, path.display().to_string()),
+        None => CheckResult::new(
+            "ffmpeg",
+            CheckStatus::Warning,
+            "ffmpeg not found. only 16KHz mono wav files can be transcribed",
+        ),
+    }
+}
+
+fn check_temp_dir() -> CheckResult {
+    let temp_dir = std::env::temp_dir();
+    let path = temp_dir.join(format!("vibe_check_{}", random_string(10)));
+    match std::fs::write(&path, b"") {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&path);
+            CheckResult::new("temp_dir", CheckStatus::Ok, temp_dir.display().to_string())
+        }
+        Err(error) => CheckResult::new(
+            "temp_dir",
+            CheckStatus::Error,
+            format!("{} is not writable: {}", temp_dir.display(), error),
+        ),
+    }
+}
+
+/// Free space of the disk with the temp directory should fit twice the largest model
+fn check_disk_space(models_folder: &Path) -> CheckResult {
+    let largest_model = model_files(models_folder)
+        .iter()
+        .filter_map(|path| path.metadata().ok().map(|metadata| metadata.len()))
+        .max()
+        .unwrap_or_default();
+    let temp_dir = std::env::temp_dir();
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let Some(disk) = disks
+        .list()
+        .iter()
+        .filter(|disk| temp_dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+    else {
+        return CheckResult::new(
+            "disk_space",
+            CheckStatus::Warning,
+            "can't find the disk of the temp directory",
+        );
+    };
+    let available = disk.available_space();
+    let message = format!(
+        "{} MB available, largest model is {} MB",
+        available / 1024 / 1024,
+        largest_model / 1024 / 1024
+    );
+    if available < largest_model * 2 {
+        return CheckResult::new("disk_space", CheckStatus::Warning, message);
+    }
+    CheckResult::new("disk_space", CheckStatus::Ok, message)
+}
+
+/// Check the environment before serving. warnings are reported and only errors fail the startup.
+/// With `skip` only the fatal checks run
+pub fn startup_checks(models_folder: &Path, model: Option<&Path>, skip: bool) -> Result<()> {
+    let mut results = vec![check_temp_dir()];
+    if !skip {
+        results.push(check_models(models_folder, model));
+        results.push(check_ffmpeg());
+        results.push(check_disk_space(models_folder));
+    }
+
+    eprintln!("Startup checks:");
+    for result in &results {
+        let label = match result.status {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Error => "error",
+        };
+        eprintln!("  [{}] {}: {}", label, result.name, result.message);
+        match result.status {
+            CheckStatus::Ok => tracing::info!(check = result.name, status = label, "{}", result.message),
+            CheckStatus::Warning => tracing::warn!(check = result.name, status = label, "{}", result.message),
+            CheckStatus::Error => tracing::error!(check = result.name, status = label, "{}", result.message),
+        }
+    }
+    if let Some(failed) = results.iter().find(|result| result.status == CheckStatus::Error) {
+        bail!("startup check {} failed: {}", failed.name, failed.message)
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Completed transcriptions kept for the rolling windows
+const MAX_RECORDS: usize = 100_000;
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+struct JobRecord {
+    completed_at: Instant,
+    audio_secs: f64,
+    latency_ms: u64,
+    success: bool,
+}
+
+#[derive(Serialize, ToSchema, Default, Clone)]
+pub struct StatsWindow {
+    pub jobs_completed: usize,
+    pub jobs_failed: usize,
+    pub total_audio_secs: f64,
+    pub avg_latency_ms: u64,
+    #[serde(skip)]
+    total_latency_ms: u64,
+}
+
+impl StatsWindow {
+    fn add(&mut self, record: &JobRecord) {
+        if record.success {
+            self.jobs_completed += 1;
+        } else {
+            self.jobs_failed += 1;
+        }
+        self.total_audio_secs += record.audio_secs;
+        self.total_latency_ms += record.latency_ms;
+        self.avg_latency_ms = self.total_latency_ms / (self.jobs_completed + self.jobs_failed) as u64;
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Stats {
+    pub last_hour: StatsWindow,
+    pub last_24h: StatsWindow,
+    /// Since the server started
+    pub all_time: StatsWindow,
+}
+
+#[derive(Default)]
+struct StatsState {
+    records: VecDeque<JobRecord>,
+    all_time: StatsWindow,
+}
+
+/// Transcription statistics of the server. kept in memory and reset on restart
+#[derive(Default)]
+pub struct StatsCollector {
+    state: Mutex<StatsState>,
+}
+
+impl StatsCollector {
+    pub fn record(&self, audio_secs: f64, latency: Duration, success: bool) {
+        let record = JobRecord {
+            completed_at: Instant::now(),
+            audio_secs,
+            latency_ms: latency.as_millis() as u64,
+            success,
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.all_time.add(&record);
+        state.records.push_back(record);
+        while state
+            .records
+            .front()
+            .is_some_and(|record| record.completed_at.elapsed() > DAY || state.records.len() > MAX_RECORDS)
+        {
+            state.records.pop_front();
+        }
+    }
+
+    /// Average request duration of the last hour, or since the server started when there were no requests in the last hour
+    pub fn avg_latency(&self) -> Duration {
+        let stats = self.stats();
+        let window = if stats.last_hour.jobs_completed + stats.last_hour.jobs_failed > 0 {
+            stats.last_hour
+        } else {
+            stats.all_time
+        };
+        Duration::from_millis(window.avg_latency_ms)
+    }
+
+    pub fn stats(&self) -> Stats {
+        let mut last_hour = StatsWindow::default();
+        let mut last_24h = StatsWindow::default();
+        let mut all_time = StatsWindow::default();
+        if let Ok(state) = self.state.lock() {
+            for record in state.records.iter().filter(|record| record.completed_at.elapsed() <= DAY) {
+                last_24h.add(record);
+                if record.completed_at.elapsed() <= HOUR {
+                    last_hour.add(record);
+                }
+            }
+            all_time = state.all_time.clone();
+        }
+        Stats {
+            last_hour,
+            last_24h,
+            all_time,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use axum::async_trait;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use validator::{Validate, ValidationErrors};
+use vibe_core::config::{self, ConfigWarningKind, TranscribeOptions};
+
+#[derive(Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// 422 response with every invalid field of the request body
+#[derive(Serialize)]
+pub struct ValidationError {
+    pub detail: String,
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationError {
+    pub fn new(detail: impl Into<String>, field: Option<&str>) -> Self {
+        Self {
+            detail: detail.into(),
+            field: field.map(|f| f.to_string()),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn from_errors(errors: &ValidationErrors, field: Option<&str>) -> Self {
+        let mut field_errors: Vec<FieldError> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(name, errors)| {
+                errors.iter().map(move |error| FieldError {
+                    field: name.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| format!("{} is out of range", name)),
+                })
+            })
+            .collect();
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+        Self {
+            detail: errors.to_string(),
+            field: field.map(|f| f.to_string()),
+            errors: field_errors,
+        }
+    }
+}
+
+impl IntoResponse for ValidationError {
+    fn into_response(self) -> Response {
+        let mut body = json!({ "error": "validation_error" });
+        if let (Some(body), Value::Object(fields)) = (body.as_object_mut(), json!(self)) {
+            body.extend(fields);
+        }
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response()
+    }
+}
+
+impl From<JsonRejection> for ValidationError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.body_text(), None)
+    }
+}
+
+/// Deserialize and validate value of request field
+pub fn from_value<T: DeserializeOwned + Validate>(value: Value, field: Option<&str>) -> Result<T, ValidationError> {
+    let value: T = serde_json::from_value(value).map_err(|e| ValidationError::new(e.to_string(), field))?;
+    value.validate().map_err(|e| ValidationError::from_errors(&e, field))?;
+    Ok(value)
+}
+
+/// Reject conflicting transcribe options. the server transcribes without diarization
+pub fn check_conflicts(options: &TranscribeOptions, field: Option<&str>) -> Result<(), ValidationError> {
+    let conflicts: Vec<FieldError> = config::validate_options(options, false)
+        .into_iter()
+        .filter(|warning| warning.kind == ConfigWarningKind::Conflict)
+        .map(|warning| FieldError {
+            field: warning.fields.join(", "),
+            message: warning.message,
+        })
+        .collect();
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    Err(ValidationError {
+        detail: "conflicting options".into(),
+        field: field.map(|f| f.to_string()),
+        errors: conflicts,
+    })
+}
+
+/// Json extractor which rejects malformed body with 422 validation_error
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ValidationError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(Self(value))
+    }
+}